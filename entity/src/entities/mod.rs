@@ -2,6 +2,18 @@
 
 pub mod prelude;
 
+pub mod ai_audit;
+pub mod ai_opt_out;
+pub mod archive_channel;
+pub mod archived_message;
+pub mod counter;
+pub mod counter_count;
 pub mod kv_store;
 pub mod quote;
+pub mod quote_purge;
+pub mod readycheck;
+pub mod readycheck_schedule;
 pub mod role_button_server;
+pub mod tldr_digest;
+pub mod tldr_summary;
+pub mod tldr_usage;
@@ -1,5 +1,17 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.0.0
 
+pub use super::ai_audit::Entity as AiAudit;
+pub use super::ai_opt_out::Entity as AiOptOut;
+pub use super::archive_channel::Entity as ArchiveChannel;
+pub use super::archived_message::Entity as ArchivedMessage;
+pub use super::counter::Entity as Counter;
+pub use super::counter_count::Entity as CounterCount;
 pub use super::kv_store::Entity as KvStore;
 pub use super::quote::Entity as Quote;
+pub use super::quote_purge::Entity as QuotePurge;
+pub use super::readycheck::Entity as Readycheck;
+pub use super::readycheck_schedule::Entity as ReadycheckSchedule;
 pub use super::role_button_server::Entity as RoleButtonServer;
+pub use super::tldr_digest::Entity as TldrDigest;
+pub use super::tldr_summary::Entity as TldrSummary;
+pub use super::tldr_usage::Entity as TldrUsage;
@@ -21,6 +21,7 @@ pub struct Model {
     #[sea_orm(column_type = "VarBinary(StringLen::None)", nullable)]
     pub attachment: Option<Vec<u8>>,
     pub attachment_name: Option<String>,
+    pub channel_archived: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
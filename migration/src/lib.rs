@@ -4,6 +4,20 @@ mod m20220101_000001_create_table;
 mod m20230329_110119_rolebuttons;
 mod m20230607_114623_ccounter;
 mod m20230614_120925_cquote_index;
+mod m20261019_100000_quote_purge;
+mod m20261019_110000_quote_channel_archived;
+mod m20261019_120000_readycheck;
+mod m20261019_130000_readycheck_schedule;
+mod m20261019_140000_readycheck_voice;
+mod m20261019_150000_readycheck_history;
+mod m20261019_160000_tldr_usage;
+mod m20261019_170000_message_archive;
+mod m20261019_180000_archived_message_reply;
+mod m20261019_190000_archived_message_extras;
+mod m20261019_200000_tldr_summary;
+mod m20261019_210000_tldr_digest;
+mod m20261019_220000_ai_privacy;
+mod m20261019_230000_counter;
 
 pub struct Migrator;
 
@@ -15,6 +29,20 @@ impl MigratorTrait for Migrator {
             Box::new(m20230329_110119_rolebuttons::Migration),
             Box::new(m20230607_114623_ccounter::Migration),
             Box::new(m20230614_120925_cquote_index::Migration),
+            Box::new(m20261019_100000_quote_purge::Migration),
+            Box::new(m20261019_110000_quote_channel_archived::Migration),
+            Box::new(m20261019_120000_readycheck::Migration),
+            Box::new(m20261019_130000_readycheck_schedule::Migration),
+            Box::new(m20261019_140000_readycheck_voice::Migration),
+            Box::new(m20261019_150000_readycheck_history::Migration),
+            Box::new(m20261019_160000_tldr_usage::Migration),
+            Box::new(m20261019_170000_message_archive::Migration),
+            Box::new(m20261019_180000_archived_message_reply::Migration),
+            Box::new(m20261019_190000_archived_message_extras::Migration),
+            Box::new(m20261019_200000_tldr_summary::Migration),
+            Box::new(m20261019_210000_tldr_digest::Migration),
+            Box::new(m20261019_220000_ai_privacy::Migration),
+            Box::new(m20261019_230000_counter::Migration),
         ]
     }
 }
@@ -0,0 +1,48 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(QuotePurge::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(QuotePurge::Id).big_integer().not_null().auto_increment().primary_key())
+                    .col(ColumnDef::new(QuotePurge::ServerId).big_unsigned().not_null())
+                    .col(ColumnDef::new(QuotePurge::PurgedBy).big_unsigned().not_null())
+                    .col(ColumnDef::new(QuotePurge::Criteria).string().not_null())
+                    .col(ColumnDef::new(QuotePurge::Removed).json().not_null())
+                    .col(ColumnDef::new(QuotePurge::Timestamp).timestamp_with_time_zone().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("quotepurge-server-id-index")
+                    .table(QuotePurge::Table)
+                    .col(QuotePurge::ServerId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(QuotePurge::Table).to_owned()).await
+    }
+}
+
+#[derive(Iden)]
+enum QuotePurge {
+    Table,
+    Id,
+    ServerId,
+    PurgedBy,
+    Criteria,
+    Removed,
+    Timestamp,
+}
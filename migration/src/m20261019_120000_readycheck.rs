@@ -0,0 +1,43 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Readycheck::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Readycheck::Id).big_integer().not_null().auto_increment().primary_key())
+                    .col(ColumnDef::new(Readycheck::ServerId).big_unsigned().not_null())
+                    .col(ColumnDef::new(Readycheck::ChannelId).big_unsigned().not_null())
+                    .col(ColumnDef::new(Readycheck::MessageId).big_unsigned().null())
+                    .col(ColumnDef::new(Readycheck::InitiatorId).big_unsigned().not_null())
+                    .col(ColumnDef::new(Readycheck::Deadline).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(Readycheck::Participants).json().not_null())
+                    .col(ColumnDef::new(Readycheck::EndedAt).timestamp_with_time_zone().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(Readycheck::Table).to_owned()).await
+    }
+}
+
+#[derive(Iden)]
+enum Readycheck {
+    Table,
+    Id,
+    ServerId,
+    ChannelId,
+    MessageId,
+    InitiatorId,
+    Deadline,
+    Participants,
+    EndedAt,
+}
@@ -0,0 +1,69 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ReadycheckSchedule::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(ReadycheckSchedule::Id).big_integer().not_null().auto_increment().primary_key())
+                    .col(ColumnDef::new(ReadycheckSchedule::ServerId).big_unsigned().not_null())
+                    .col(ColumnDef::new(ReadycheckSchedule::ChannelId).big_unsigned().not_null())
+                    .col(ColumnDef::new(ReadycheckSchedule::CreatorId).big_unsigned().not_null())
+                    .col(ColumnDef::new(ReadycheckSchedule::Roles).json().not_null())
+                    .col(ColumnDef::new(ReadycheckSchedule::Users).json().not_null())
+                    .col(ColumnDef::new(ReadycheckSchedule::Duration).integer().not_null())
+                    .col(ColumnDef::new(ReadycheckSchedule::Reminder).integer().null())
+                    .col(ColumnDef::new(ReadycheckSchedule::Time).time().not_null())
+                    .col(ColumnDef::new(ReadycheckSchedule::Timezone).string().not_null())
+                    .col(ColumnDef::new(ReadycheckSchedule::Repeat).string().not_null())
+                    .col(ColumnDef::new(ReadycheckSchedule::NextRun).timestamp_with_time_zone().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Readycheck::Table)
+                    .add_column(ColumnDef::new(Readycheck::ReminderAt).timestamp_with_time_zone().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(Table::alter().table(Readycheck::Table).drop_column(Readycheck::ReminderAt).to_owned())
+            .await?;
+        manager.drop_table(Table::drop().table(ReadycheckSchedule::Table).to_owned()).await
+    }
+}
+
+#[derive(Iden)]
+enum ReadycheckSchedule {
+    Table,
+    Id,
+    ServerId,
+    ChannelId,
+    CreatorId,
+    Roles,
+    Users,
+    Duration,
+    Reminder,
+    Time,
+    Timezone,
+    Repeat,
+    NextRun,
+}
+
+#[derive(Iden)]
+enum Readycheck {
+    Table,
+    ReminderAt,
+}
@@ -0,0 +1,65 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Readycheck::Table)
+                    .add_column(ColumnDef::new(Readycheck::VoiceChannelId).big_unsigned().null())
+                    .add_column(ColumnDef::new(Readycheck::LeaveNotReady).boolean().not_null().default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ReadycheckSchedule::Table)
+                    .add_column(ColumnDef::new(ReadycheckSchedule::VoiceChannelId).big_unsigned().null())
+                    .add_column(ColumnDef::new(ReadycheckSchedule::LeaveNotReady).boolean().not_null().default(false))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ReadycheckSchedule::Table)
+                    .drop_column(ReadycheckSchedule::VoiceChannelId)
+                    .drop_column(ReadycheckSchedule::LeaveNotReady)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Readycheck::Table)
+                    .drop_column(Readycheck::VoiceChannelId)
+                    .drop_column(Readycheck::LeaveNotReady)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Readycheck {
+    Table,
+    VoiceChannelId,
+    LeaveNotReady,
+}
+
+#[derive(Iden)]
+enum ReadycheckSchedule {
+    Table,
+    VoiceChannelId,
+    LeaveNotReady,
+}
@@ -0,0 +1,53 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Readycheck::Table)
+                    .add_column(ColumnDef::new(Readycheck::StartedAt).timestamp_with_time_zone().null())
+                    .add_column(ColumnDef::new(Readycheck::Outcome).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("readycheck-server-id-index")
+                    .table(Readycheck::Table)
+                    .col(Readycheck::ServerId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(Index::drop().name("readycheck-server-id-index").table(Readycheck::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Readycheck::Table)
+                    .drop_column(Readycheck::StartedAt)
+                    .drop_column(Readycheck::Outcome)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Readycheck {
+    Table,
+    ServerId,
+    StartedAt,
+    Outcome,
+}
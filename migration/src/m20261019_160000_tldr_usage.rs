@@ -0,0 +1,38 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TldrUsage::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(TldrUsage::ServerId).big_unsigned().not_null())
+                    .col(ColumnDef::new(TldrUsage::UserId).big_unsigned().not_null())
+                    .col(ColumnDef::new(TldrUsage::Day).date().not_null())
+                    .col(ColumnDef::new(TldrUsage::Tokens).big_integer().not_null())
+                    .col(ColumnDef::new(TldrUsage::Requests).integer().not_null())
+                    .primary_key(Index::create().col(TldrUsage::ServerId).col(TldrUsage::UserId).col(TldrUsage::Day))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(TldrUsage::Table).to_owned()).await
+    }
+}
+
+#[derive(Iden)]
+enum TldrUsage {
+    Table,
+    ServerId,
+    UserId,
+    Day,
+    Tokens,
+    Requests,
+}
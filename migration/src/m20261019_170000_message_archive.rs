@@ -0,0 +1,79 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ArchiveChannel::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(ArchiveChannel::ChannelId).big_unsigned().not_null().primary_key())
+                    .col(ColumnDef::new(ArchiveChannel::ServerId).big_unsigned().not_null())
+                    .col(ColumnDef::new(ArchiveChannel::RetentionDays).integer().not_null())
+                    .col(ColumnDef::new(ArchiveChannel::CoveredSince).timestamp_with_time_zone().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ArchivedMessage::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(ArchivedMessage::MessageId).big_unsigned().not_null().primary_key())
+                    .col(ColumnDef::new(ArchivedMessage::ServerId).big_unsigned().not_null())
+                    .col(ColumnDef::new(ArchivedMessage::ChannelId).big_unsigned().not_null())
+                    .col(ColumnDef::new(ArchivedMessage::AuthorId).big_unsigned().not_null())
+                    .col(ColumnDef::new(ArchivedMessage::Author).string().not_null())
+                    .col(ColumnDef::new(ArchivedMessage::Content).text().not_null())
+                    .col(ColumnDef::new(ArchivedMessage::Timestamp).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(ArchivedMessage::EditedAt).timestamp_with_time_zone().null())
+                    .col(ColumnDef::new(ArchivedMessage::ReplyToAuthor).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("archived-message-channel-timestamp-index")
+                    .table(ArchivedMessage::Table)
+                    .col(ArchivedMessage::ChannelId)
+                    .col(ArchivedMessage::Timestamp)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(ArchivedMessage::Table).to_owned()).await?;
+        manager.drop_table(Table::drop().table(ArchiveChannel::Table).to_owned()).await
+    }
+}
+
+#[derive(Iden)]
+enum ArchiveChannel {
+    Table,
+    ChannelId,
+    ServerId,
+    RetentionDays,
+    CoveredSince,
+}
+
+#[derive(Iden)]
+enum ArchivedMessage {
+    Table,
+    MessageId,
+    ServerId,
+    ChannelId,
+    AuthorId,
+    Author,
+    Content,
+    Timestamp,
+    EditedAt,
+    ReplyToAuthor,
+}
@@ -0,0 +1,30 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ArchivedMessage::Table)
+                    .add_column(ColumnDef::new(ArchivedMessage::ReplyTo).big_unsigned().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(Table::alter().table(ArchivedMessage::Table).drop_column(ArchivedMessage::ReplyTo).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum ArchivedMessage {
+    Table,
+    ReplyTo,
+}
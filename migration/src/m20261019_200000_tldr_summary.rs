@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TldrSummary::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(TldrSummary::Id).big_integer().not_null().auto_increment().primary_key())
+                    .col(ColumnDef::new(TldrSummary::ServerId).big_unsigned().not_null())
+                    .col(ColumnDef::new(TldrSummary::ChannelId).big_unsigned().not_null())
+                    .col(ColumnDef::new(TldrSummary::UserId).big_unsigned().null())
+                    .col(ColumnDef::new(TldrSummary::RepliesTo).big_unsigned().null())
+                    .col(ColumnDef::new(TldrSummary::FirstMessageId).big_unsigned().not_null())
+                    .col(ColumnDef::new(TldrSummary::LastMessageId).big_unsigned().not_null())
+                    .col(ColumnDef::new(TldrSummary::MessageCount).integer().not_null())
+                    .col(ColumnDef::new(TldrSummary::Text).text().not_null())
+                    .col(ColumnDef::new(TldrSummary::CreatedAt).timestamp_with_time_zone().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("tldr-summary-channel-id-index")
+                    .table(TldrSummary::Table)
+                    .col(TldrSummary::ChannelId)
+                    .col(TldrSummary::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(TldrSummary::Table).to_owned()).await
+    }
+}
+
+#[derive(Iden)]
+enum TldrSummary {
+    Table,
+    Id,
+    ServerId,
+    ChannelId,
+    UserId,
+    RepliesTo,
+    FirstMessageId,
+    LastMessageId,
+    MessageCount,
+    Text,
+    CreatedAt,
+}
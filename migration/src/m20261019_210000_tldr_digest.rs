@@ -0,0 +1,41 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TldrDigest::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(TldrDigest::ChannelId).big_unsigned().not_null().primary_key())
+                    .col(ColumnDef::new(TldrDigest::ServerId).big_unsigned().not_null())
+                    .col(ColumnDef::new(TldrDigest::DigestChannelId).big_unsigned().not_null())
+                    .col(ColumnDef::new(TldrDigest::MinMessages).integer().not_null())
+                    .col(ColumnDef::new(TldrDigest::Time).time().not_null())
+                    .col(ColumnDef::new(TldrDigest::Timezone).string().not_null())
+                    .col(ColumnDef::new(TldrDigest::NextRun).timestamp_with_time_zone().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(TldrDigest::Table).to_owned()).await
+    }
+}
+
+#[derive(Iden)]
+enum TldrDigest {
+    Table,
+    ChannelId,
+    ServerId,
+    DigestChannelId,
+    MinMessages,
+    Time,
+    Timezone,
+    NextRun,
+}
@@ -0,0 +1,76 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AiOptOut::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(AiOptOut::ServerId).big_unsigned().not_null())
+                    .col(ColumnDef::new(AiOptOut::TargetId).big_unsigned().not_null())
+                    .col(ColumnDef::new(AiOptOut::Kind).string().not_null())
+                    .col(ColumnDef::new(AiOptOut::CreatedAt).timestamp_with_time_zone().not_null())
+                    .primary_key(Index::create().col(AiOptOut::ServerId).col(AiOptOut::TargetId))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(AiAudit::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(AiAudit::Id).big_integer().not_null().auto_increment().primary_key())
+                    .col(ColumnDef::new(AiAudit::ServerId).big_unsigned().not_null())
+                    .col(ColumnDef::new(AiAudit::ChannelId).big_unsigned().not_null())
+                    .col(ColumnDef::new(AiAudit::UserId).big_unsigned().not_null())
+                    .col(ColumnDef::new(AiAudit::Feature).string().not_null())
+                    .col(ColumnDef::new(AiAudit::MessageIds).json().not_null())
+                    .col(ColumnDef::new(AiAudit::CreatedAt).timestamp_with_time_zone().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("ai-audit-server-id-index")
+                    .table(AiAudit::Table)
+                    .col(AiAudit::ServerId)
+                    .col(AiAudit::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(AiAudit::Table).to_owned()).await?;
+        manager.drop_table(Table::drop().table(AiOptOut::Table).to_owned()).await
+    }
+}
+
+#[derive(Iden)]
+enum AiOptOut {
+    Table,
+    ServerId,
+    TargetId,
+    Kind,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum AiAudit {
+    Table,
+    Id,
+    ServerId,
+    ChannelId,
+    UserId,
+    Feature,
+    MessageIds,
+    CreatedAt,
+}
@@ -0,0 +1,75 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Counter::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Counter::Id).big_integer().not_null().auto_increment().primary_key())
+                    .col(ColumnDef::new(Counter::ServerId).big_unsigned().not_null())
+                    .col(ColumnDef::new(Counter::Name).string().not_null())
+                    .col(ColumnDef::new(Counter::Words).json().null())
+                    .col(ColumnDef::new(Counter::Pattern).string().null())
+                    .col(ColumnDef::new(Counter::ChannelId).big_unsigned().null())
+                    .col(ColumnDef::new(Counter::Command).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("counter-server-id-name-index")
+                    .table(Counter::Table)
+                    .col(Counter::ServerId)
+                    .col(Counter::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(CounterCount::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(CounterCount::CounterId).big_integer().not_null())
+                    .col(ColumnDef::new(CounterCount::UserId).big_unsigned().not_null())
+                    .col(ColumnDef::new(CounterCount::Count).big_integer().not_null())
+                    .primary_key(Index::create().col(CounterCount::CounterId).col(CounterCount::UserId))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(CounterCount::Table).to_owned()).await?;
+        manager.drop_table(Table::drop().table(Counter::Table).to_owned()).await
+    }
+}
+
+#[derive(Iden)]
+enum Counter {
+    Table,
+    Id,
+    ServerId,
+    Name,
+    Words,
+    Pattern,
+    ChannelId,
+    Command,
+}
+
+#[derive(Iden)]
+enum CounterCount {
+    Table,
+    CounterId,
+    UserId,
+    Count,
+}
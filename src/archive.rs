@@ -0,0 +1,256 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, Utc};
+use sea_orm::{
+    sea_query::OnConflict, ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, EntityTrait,
+    IntoActiveModel, QueryFilter,
+};
+use serde_json::json;
+use serenity::{
+    client::Context,
+    futures::StreamExt,
+    model::{
+        channel::Message,
+        event::MessageUpdateEvent,
+        id::{ChannelId, GuildId, MessageId},
+        user::User,
+    },
+    utils::{content_safe, ContentSafeOptions},
+};
+use tokio::time::sleep;
+
+use entity::{
+    archive_channel, archived_message,
+    prelude::{ArchiveChannel, ArchivedMessage},
+};
+
+use crate::{quote::truncate, util::DatabaseTypeMapKey};
+
+// How often messages that are past their channel's retention get removed
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// How many messages are written to the database at once while backfilling
+const BACKFILL_BATCH: usize = 100;
+
+// How much of an embed's description we keep
+const MAX_EMBED_LENGTH: usize = 300;
+
+// The ready event fires on every reconnect, but we only want to prune from one loop
+static PRUNING: AtomicBool = AtomicBool::new(false);
+
+/// Returns the archive settings of a channel, if it is being archived.
+pub(crate) async fn channel(db: &DatabaseConnection, channel_id: ChannelId) -> Result<Option<archive_channel::Model>> {
+    Ok(ArchiveChannel::find_by_id(channel_id.get() as i64).one(db).await?)
+}
+
+/// The oldest message a channel keeps around.
+pub(crate) fn cutoff(channel: &archive_channel::Model) -> DateTime<Utc> {
+    Utc::now() - chrono::Duration::days(channel.retention_days as i64)
+}
+
+/// Stores a new message, if it was posted in an archived channel.
+pub(crate) async fn message(ctx: &Context, msg: &Message) -> Result<()> {
+    let Some(guild_id) = msg.guild_id else { return Ok(()) };
+    if msg.author.bot {
+        return Ok(());
+    }
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    if channel(&db, msg.channel_id).await?.is_none() {
+        return Ok(());
+    }
+    store(&db, vec![to_model(ctx, guild_id, msg)]).await
+}
+
+/// Keeps the archived copy of a message in line with its edits, and the embeds Discord adds to it later on.
+pub(crate) async fn message_update(ctx: &Context, new: Option<&Message>, event: &MessageUpdateEvent) -> Result<()> {
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let Some(archived) = ArchivedMessage::find_by_id(event.id.get() as i64).one(&db).await? else { return Ok(()) };
+
+    // The cache has the whole message after the update, otherwise all we can go by is what the event changed
+    if let Some(new) = new {
+        return store(&db, vec![to_model(ctx, GuildId::new(archived.server_id as u64), new)]).await;
+    }
+    let Some(content) = &event.content else { return Ok(()) };
+
+    let guild_id = GuildId::new(archived.server_id as u64);
+    let mentions = event.mentions.as_deref().unwrap_or_default();
+
+    let mut archived = archived.into_active_model();
+    archived.content = Set(safe_content(ctx, guild_id, content, mentions));
+    archived.edited_at = Set(Some(
+        event.edited_timestamp.map(|t| *t).unwrap_or_else(Utc::now).with_timezone(&FixedOffset::east_opt(0).unwrap()),
+    ));
+    archived.update(&db).await?;
+    Ok(())
+}
+
+/// Forgets messages that got deleted from Discord.
+pub(crate) async fn message_delete(ctx: &Context, message_ids: &[MessageId]) -> Result<()> {
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    ArchivedMessage::delete_many()
+        .filter(archived_message::Column::MessageId.is_in(message_ids.iter().map(|id| id.get() as i64)))
+        .exec(&db)
+        .await?;
+    Ok(())
+}
+
+/// Archives the history of a channel that is already there, newest first, until either the retention or the limit is
+/// reached. Returns how many messages were read.
+pub(crate) async fn backfill(ctx: &Context, channel: &archive_channel::Model, limit: usize) -> Result<usize> {
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let guild_id = GuildId::new(channel.server_id as u64);
+    let cutoff = cutoff(channel);
+
+    let mut read = 0;
+    let mut oldest = None;
+    let mut complete = true;
+    let mut batch = Vec::with_capacity(BACKFILL_BATCH);
+    let mut messages = ChannelId::new(channel.channel_id as u64).messages_iter(ctx).boxed();
+    while let Some(message) = messages.next().await {
+        let message = message?;
+        if message.timestamp.to_utc() < cutoff {
+            break;
+        }
+        if read >= limit {
+            complete = false;
+            break;
+        }
+
+        read += 1;
+        oldest = Some(message.timestamp.to_utc());
+        if !message.author.bot {
+            batch.push(to_model(ctx, guild_id, &message));
+        }
+        if batch.len() >= BACKFILL_BATCH {
+            store(&db, std::mem::take(&mut batch)).await?;
+        }
+    }
+    store(&db, batch).await?;
+
+    // If we went all the way back, the archive covers everything the retention allows for
+    let covered_since = match (complete, oldest) {
+        (false, Some(oldest)) => oldest,
+        _ => cutoff,
+    };
+    if covered_since < channel.covered_since.to_utc() {
+        let mut channel = channel.clone().into_active_model();
+        channel.covered_since = Set(covered_since.with_timezone(&FixedOffset::east_opt(0).unwrap()));
+        channel.update(&db).await?;
+    }
+    Ok(read)
+}
+
+/// Removes archived messages once they're past their channel's retention. Only starts a loop on the first call.
+pub(crate) async fn prune(ctx: Context) {
+    if PRUNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    loop {
+        if let Err(e) = prune_channels(&ctx).await {
+            error!("Could not prune the message archive: {e}");
+        }
+        sleep(PRUNE_INTERVAL).await;
+    }
+}
+
+async fn prune_channels(ctx: &Context) -> Result<()> {
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    for channel in ArchiveChannel::find().all(&db).await? {
+        let cutoff = cutoff(&channel);
+        ArchivedMessage::delete_many()
+            .filter(archived_message::Column::ChannelId.eq(channel.channel_id))
+            .filter(archived_message::Column::Timestamp.lt(cutoff))
+            .exec(&db)
+            .await?;
+
+        if channel.covered_since.to_utc() < cutoff {
+            let mut channel = channel.into_active_model();
+            channel.covered_since = Set(cutoff.with_timezone(&FixedOffset::east_opt(0).unwrap()));
+            channel.update(&db).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn store(db: &DatabaseConnection, messages: Vec<archived_message::ActiveModel>) -> Result<()> {
+    if messages.is_empty() {
+        return Ok(());
+    }
+    ArchivedMessage::insert_many(messages)
+        .on_conflict(
+            OnConflict::column(archived_message::Column::MessageId)
+                .update_columns([
+                    archived_message::Column::Content,
+                    archived_message::Column::EditedAt,
+                    archived_message::Column::Extras,
+                ])
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+    Ok(())
+}
+
+fn to_model(ctx: &Context, guild_id: GuildId, msg: &Message) -> archived_message::ActiveModel {
+    let nick = msg.member.as_ref().and_then(|member| member.nick.clone());
+    let utc = FixedOffset::east_opt(0).unwrap();
+    archived_message::ActiveModel {
+        message_id: Set(msg.id.get() as i64),
+        server_id: Set(guild_id.get() as i64),
+        channel_id: Set(msg.channel_id.get() as i64),
+        author_id: Set(msg.author.id.get() as i64),
+        author: Set(nick.unwrap_or_else(|| display_name(ctx, guild_id, &msg.author))),
+        content: Set(safe_content(ctx, guild_id, &msg.content, &msg.mentions)),
+        timestamp: Set(msg.timestamp.with_timezone(&utc)),
+        edited_at: Set(msg.edited_timestamp.map(|t| t.with_timezone(&utc))),
+        extras: Set(Some(extras(msg)).filter(|extras| !extras.is_empty()).map(|extras| json!(extras))),
+        reply_to: Set(msg.message_reference.as_ref().and_then(|r| r.message_id).map(|id| id.get() as i64)),
+        reply_to_author: Set(msg.referenced_message.as_ref().map(|reply| display_name(ctx, guild_id, &reply.author))),
+    }
+}
+
+/// Describes everything in a message besides its text, like attachments, embeds, polls and stickers.
+pub(crate) fn extras(msg: &Message) -> Vec<String> {
+    let mut extras = Vec::new();
+    for attachment in &msg.attachments {
+        match &attachment.description {
+            Some(description) => extras.push(format!("attached {} ({description})", attachment.filename)),
+            None => extras.push(format!("attached {}", attachment.filename)),
+        }
+    }
+    for embed in &msg.embeds {
+        let text: Vec<&str> = [&embed.title, &embed.description].into_iter().flatten().map(String::as_str).collect();
+        if !text.is_empty() {
+            extras.push(format!("embedded \"{}\"", truncate(&text.join(": "), MAX_EMBED_LENGTH)));
+        }
+    }
+    if let Some(poll) = &msg.poll {
+        let answers: Vec<&str> = poll.answers.iter().filter_map(|answer| answer.poll_media.text.as_deref()).collect();
+        extras.push(format!(
+            "started a poll \"{}\" with the answers {}",
+            poll.question.text.as_deref().unwrap_or_default(),
+            answers.join(", ")
+        ));
+    }
+    for sticker in &msg.sticker_items {
+        extras.push(format!("sent the sticker {}", sticker.name));
+    }
+    extras
+}
+
+/// Replaces mentions by names, so the archive reads the same as the channel did.
+fn safe_content(ctx: &Context, guild_id: GuildId, content: &str, mentions: &[User]) -> String {
+    content_safe(&ctx.cache, content, &ContentSafeOptions::new().display_as_member_from(guild_id), mentions)
+}
+
+/// The name someone goes by in the guild, as far as the cache knows.
+fn display_name(ctx: &Context, guild_id: GuildId, user: &User) -> String {
+    let member =
+        ctx.cache.guild(guild_id).and_then(|guild| guild.members.get(&user.id).map(|m| m.display_name().to_string()));
+    member.unwrap_or_else(|| user.global_name.clone().unwrap_or_else(|| user.name.clone()))
+}
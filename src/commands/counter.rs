@@ -0,0 +1,618 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    env,
+    sync::{Arc, Mutex, MutexGuard, OnceLock},
+};
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    ActiveModelTrait,
+    ActiveValue::Set,
+    ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, QueryFilter, QueryOrder, QuerySelect,
+    TransactionTrait,
+};
+use serde::Deserialize;
+use serde_json::json;
+use serenity::{
+    all::{Command, CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType},
+    builder::{
+        CreateAutocompleteResponse, CreateCommand, CreateCommandOption, CreateEmbed, CreateInteractionResponse,
+        CreateInteractionResponseMessage,
+    },
+    client::Context,
+    model::{
+        channel::Message,
+        id::{ChannelId, GuildId, UserId},
+        Colour,
+    },
+    prelude::Mentionable,
+};
+
+use entity::{
+    counter, counter_count,
+    prelude::{Counter, CounterCount, KvStore},
+};
+
+use crate::{
+    commands::{
+        quote::{MAX_SUGGESTIONS, MAX_SUGGESTION_LENGTH},
+        send_ephemeral_message,
+    },
+    quote::{like_pattern, truncate},
+    util::{kvstore, DatabaseTypeMapKey},
+};
+
+// Discord doesn't allow longer command names, and we keep counter names to the same
+const MAX_NAME_LENGTH: usize = 32;
+const MAX_WORDS: usize = 25;
+const MAX_PATTERN_LENGTH: usize = 200;
+// Discord doesn't allow longer messages
+const MAX_MESSAGE_LENGTH: usize = 2000;
+// How many members the totals list
+const TOP_USERS: usize = 5;
+
+// The one counter there used to be, which had its command registered globally and its count in the key-value store
+const LEGACY_COMMAND: &str = "cum";
+const LEGACY_KEY: &str = "ccounter";
+// The guild the bot was made for, which gets the count of the old counter
+const LEGACY_GUILD_ENV: &str = "WEB_WHITELIST_GUILD_ID";
+
+/// A counter, ready to be matched against messages.
+struct Matcher {
+    id: i64,
+    channel_id: Option<ChannelId>,
+    words: HashSet<String>,
+    pattern: Option<Regex>,
+}
+
+impl Matcher {
+    fn new(model: &counter::Model) -> Self {
+        let words: Vec<String> =
+            model.words.clone().and_then(|words| serde_json::from_value(words).ok()).unwrap_or_default();
+        Self {
+            id: model.id,
+            channel_id: model.channel_id.map(|channel_id| ChannelId::new(channel_id as u64)),
+            words: words.into_iter().collect(),
+            // Patterns are checked when they're set, so this only fails if the regex crate changed its mind
+            pattern: model
+                .pattern
+                .as_deref()
+                .filter(|pattern| !pattern.is_empty())
+                .and_then(|pattern| Regex::new(pattern).ok()),
+        }
+    }
+
+    fn matches(&self, channel_id: ChannelId, content: &str, words: &HashSet<String>) -> bool {
+        if self.channel_id.is_some_and(|scope| scope != channel_id) {
+            return false;
+        }
+        !self.words.is_disjoint(words) || self.pattern.as_ref().is_some_and(|pattern| pattern.is_match(content))
+    }
+}
+
+// The counters of every guild, so we don't need the database for every message that comes in.
+// This is only ever locked briefly, and never across an await
+static MATCHERS: OnceLock<Mutex<HashMap<GuildId, Arc<Vec<Matcher>>>>> = OnceLock::new();
+
+fn matchers() -> MutexGuard<'static, HashMap<GuildId, Arc<Vec<Matcher>>>> {
+    MATCHERS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner())
+}
+
+async fn guild_matchers(db: &DatabaseConnection, guild_id: GuildId) -> Result<Arc<Vec<Matcher>>> {
+    if let Some(matchers) = matchers().get(&guild_id) {
+        return Ok(matchers.clone());
+    }
+
+    let counters = Counter::find().filter(counter::Column::ServerId.eq(guild_id.get())).all(db).await?;
+    let guild_matchers = Arc::new(counters.iter().map(Matcher::new).collect::<Vec<_>>());
+    matchers().insert(guild_id, guild_matchers.clone());
+    Ok(guild_matchers)
+}
+
+/// Makes the next message read the counters of the guild again, after they changed.
+fn forget(guild_id: GuildId) {
+    matchers().remove(&guild_id);
+}
+
+/// Splits text into lowercase words, leaving out anything that isn't a letter.
+fn split_words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split_whitespace()
+        .map(|word| word.chars().filter(|c| c.is_alphabetic()).flat_map(char::to_lowercase).collect::<String>())
+        .filter(|word| !word.is_empty())
+}
+
+pub(crate) async fn handle_ingress(ctx: &Context, msg: &Message) -> Result<()> {
+    let Some(guild_id) = msg.guild_id else { return Ok(()) };
+    // Filter out bot messages
+    if msg.author.bot {
+        return Ok(());
+    }
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let matchers = guild_matchers(&db, guild_id).await?;
+    if matchers.is_empty() {
+        return Ok(());
+    }
+
+    // Every counter goes up at most once per message
+    let words: HashSet<String> = split_words(&msg.content).collect();
+    for matcher in matchers.iter().filter(|matcher| matcher.matches(msg.channel_id, &msg.content, &words)) {
+        increment(&db, matcher.id, msg.author.id).await?;
+    }
+    Ok(())
+}
+
+async fn increment(db: &DatabaseConnection, counter_id: i64, user_id: UserId) -> Result<()> {
+    let count =
+        counter_count::ActiveModel { counter_id: Set(counter_id), user_id: Set(user_id.get() as i64), count: Set(1) };
+    CounterCount::insert(count)
+        .on_conflict(
+            OnConflict::columns([counter_count::Column::CounterId, counter_count::Column::UserId])
+                .value(counter_count::Column::Count, Expr::col((CounterCount, counter_count::Column::Count)).add(1))
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+    Ok(())
+}
+
+pub(super) async fn register(ctx: &Context) -> Result<()> {
+    let name_option = |description: &str| {
+        CreateCommandOption::new(CommandOptionType::String, "name", description).required(true).set_autocomplete(true)
+    };
+
+    Command::create_global_command(
+        ctx,
+        CreateCommand::new("counter")
+            .description("Counts how often words are said")
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "show", "Shows how often a counter went up")
+                    .add_sub_option(name_option("The counter to show")),
+            )
+            .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "list", "Lists the counters"))
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "set", "Creates or changes a counter")
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::String, "name", "What to call the counter")
+                            .required(true)
+                            .max_length(MAX_NAME_LENGTH as u16),
+                    )
+                    .add_sub_option(CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "words",
+                        "The words to count, separated by commas",
+                    ))
+                    .add_sub_option(
+                        CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "pattern",
+                            "A regular expression to count instead, or as well",
+                        )
+                        .max_length(MAX_PATTERN_LENGTH as u16),
+                    )
+                    .add_sub_option(CreateCommandOption::new(
+                        CommandOptionType::Channel,
+                        "channel",
+                        "Only count in this channel",
+                    ))
+                    .add_sub_option(
+                        CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "command",
+                            "A command of its own that shows the counter",
+                        )
+                        .max_length(MAX_NAME_LENGTH as u16),
+                    ),
+            )
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "delete", "Removes a counter")
+                    .add_sub_option(name_option("The counter to remove")),
+            ),
+    )
+    .await?;
+
+    // The hard-coded counter had its command registered for every guild, counters now have theirs per guild
+    for command in Command::get_global_commands(ctx).await? {
+        if command.name == LEGACY_COMMAND {
+            Command::delete_global_command(ctx, command.id).await?;
+        }
+    }
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    migrate_legacy(ctx, &db).await?;
+    for counter in Counter::find().filter(counter::Column::Command.is_not_null()).all(&db).await? {
+        if let Err(e) = create_alias(ctx, &counter).await {
+            error!("Could not register the command of counter {}: {e}", counter.id);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct LegacyCounter {
+    count: u32,
+}
+
+/// Moves the count of the old counter into a counter of the guild the bot was made for, so it isn't lost.
+/// Nobody knows who said what back then, so the bot gets the credit for all of it.
+async fn migrate_legacy(ctx: &Context, db: &DatabaseConnection) -> Result<()> {
+    let Some(legacy) = kvstore::get::<LegacyCounter>(db, LEGACY_KEY).await? else { return Ok(()) };
+    let Some(guild_id) = env::var(LEGACY_GUILD_ENV).ok().and_then(|id| id.parse::<u64>().ok()).map(GuildId::new) else {
+        warn!("Not moving the old counter, as {LEGACY_GUILD_ENV} isn't set");
+        return Ok(());
+    };
+
+    let txn = db.begin().await?;
+    let existing = Counter::find()
+        .filter(counter::Column::ServerId.eq(guild_id.get()))
+        .filter(counter::Column::Name.eq(LEGACY_COMMAND))
+        .one(&txn)
+        .await?;
+    let counter_id = match existing {
+        Some(existing) => existing.id,
+        None => {
+            // The command might already have been given to another counter
+            let taken = Counter::find()
+                .filter(counter::Column::ServerId.eq(guild_id.get()))
+                .filter(counter::Column::Command.eq(LEGACY_COMMAND))
+                .one(&txn)
+                .await?
+                .is_some();
+            counter::ActiveModel {
+                id: Default::default(),
+                server_id: Set(guild_id.get() as i64),
+                name: Set(LEGACY_COMMAND.to_string()),
+                words: Set(Some(json!([LEGACY_COMMAND]))),
+                pattern: Set(None),
+                channel_id: Set(None),
+                command: Set((!taken).then(|| LEGACY_COMMAND.to_string())),
+            }
+            .insert(&txn)
+            .await?
+            .id
+        }
+    };
+
+    let count = counter_count::ActiveModel {
+        counter_id: Set(counter_id),
+        user_id: Set(ctx.cache.current_user().id.get() as i64),
+        count: Set(legacy.count as i64),
+    };
+    CounterCount::insert(count)
+        .on_conflict(
+            OnConflict::columns([counter_count::Column::CounterId, counter_count::Column::UserId])
+                .value(
+                    counter_count::Column::Count,
+                    Expr::col((CounterCount, counter_count::Column::Count)).add(legacy.count as i64),
+                )
+                .to_owned(),
+        )
+        .exec_without_returning(&txn)
+        .await?;
+    KvStore::delete_by_id(LEGACY_KEY).exec(&txn).await?;
+    txn.commit().await?;
+
+    forget(guild_id);
+    info!("Moved the old counter's count of {} into counter {counter_id} of guild {guild_id}", legacy.count);
+    Ok(())
+}
+
+/// Registers the command a counter can be shown with in its guild.
+async fn create_alias(ctx: &Context, counter: &counter::Model) -> Result<()> {
+    let Some(command) = &counter.command else { return Ok(()) };
+    GuildId::new(counter.server_id as u64)
+        .create_command(
+            ctx,
+            CreateCommand::new(command).description(format!("Shows how often {} was counted", counter.name)),
+        )
+        .await?;
+    Ok(())
+}
+
+async fn remove_alias(ctx: &Context, guild_id: GuildId, command: &str) -> Result<()> {
+    for registered in guild_id.get_commands(ctx).await? {
+        if registered.name == command {
+            guild_id.delete_command(ctx, registered.id).await?;
+        }
+    }
+    Ok(())
+}
+
+pub(super) async fn handle_command(ctx: Context, cmd: CommandInteraction) -> Result<()> {
+    let Some((subcommand, options)) = cmd.data.options.first().and_then(|option| match &option.value {
+        CommandDataOptionValue::SubCommand(options) => Some((option.name.clone(), options.clone())),
+        _ => None,
+    }) else {
+        return send_ephemeral_message(ctx, cmd, "No subcommand passed").await;
+    };
+
+    match subcommand.as_str() {
+        "show" => handle_show(ctx, cmd, &options).await,
+        "list" => handle_list(ctx, cmd).await,
+        "set" => handle_set(ctx, cmd, &options).await,
+        "delete" => handle_delete(ctx, cmd, &options).await,
+        _ => send_ephemeral_message(ctx, cmd, "Unknown subcommand").await,
+    }
+}
+
+/// Handles the command a counter was given of its own.
+pub(super) async fn handle_alias(ctx: Context, cmd: CommandInteraction) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Err(anyhow!("Unknown command received: {}", cmd.data.name)) };
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let Some(counter) = Counter::find()
+        .filter(counter::Column::ServerId.eq(guild_id.get()))
+        .filter(counter::Column::Command.eq(cmd.data.name.as_str()))
+        .one(&db)
+        .await?
+    else {
+        return Err(anyhow!("Unknown command received: {}", cmd.data.name));
+    };
+    show(ctx, cmd, &db, counter).await
+}
+
+fn name_option(options: &[CommandDataOption]) -> Option<String> {
+    options.iter().find_map(|option| match &option.value {
+        CommandDataOptionValue::String(name) if option.name == "name" => Some(name.trim().to_lowercase()),
+        _ => None,
+    })
+}
+
+async fn find(db: &DatabaseConnection, guild_id: GuildId, name: &str) -> Result<Option<counter::Model>> {
+    Ok(Counter::find()
+        .filter(counter::Column::ServerId.eq(guild_id.get()))
+        .filter(counter::Column::Name.eq(name))
+        .one(db)
+        .await?)
+}
+
+async fn handle_show(ctx: Context, cmd: CommandInteraction, options: &[CommandDataOption]) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+    let Some(name) = name_option(options) else { return send_ephemeral_message(ctx, cmd, "No name passed.").await };
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let Some(counter) = find(&db, guild_id, &name).await? else {
+        return send_ephemeral_message(ctx, cmd, "There is no counter with that name.").await;
+    };
+    show(ctx, cmd, &db, counter).await
+}
+
+async fn show(ctx: Context, cmd: CommandInteraction, db: &DatabaseConnection, counter: counter::Model) -> Result<()> {
+    let mut counts = CounterCount::find().filter(counter_count::Column::CounterId.eq(counter.id)).all(db).await?;
+    counts.sort_by_key(|count| Reverse(count.count));
+
+    let total: i64 = counts.iter().map(|count| count.count).sum();
+    let plural = if total == 1 { "time" } else { "times" };
+    let mut response =
+        CreateInteractionResponseMessage::new().content(format!("I have seen {} {total} {plural}.", counter.name));
+
+    // Mentions in an embed don't ping anyone
+    if !counts.is_empty() {
+        let top: Vec<String> = counts
+            .iter()
+            .take(TOP_USERS)
+            .map(|count| format!("{}: {}", UserId::new(count.user_id as u64).mention(), count.count))
+            .collect();
+        response = response
+            .embed(CreateEmbed::new().title("Most often").colour(Colour::FABLED_PINK).description(top.join("\n")));
+    }
+
+    cmd.create_response(ctx, CreateInteractionResponse::Message(response)).await?;
+    Ok(())
+}
+
+async fn handle_list(ctx: Context, cmd: CommandInteraction) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let counters = Counter::find()
+        .filter(counter::Column::ServerId.eq(guild_id.get()))
+        .order_by_asc(counter::Column::Name)
+        .all(&db)
+        .await?;
+    if counters.is_empty() {
+        return send_ephemeral_message(ctx, cmd, "There are no counters.").await;
+    }
+
+    let lines: Vec<String> = counters.iter().map(describe).collect();
+    send_ephemeral_message(ctx, cmd, &truncate(&lines.join("\n"), MAX_MESSAGE_LENGTH)).await
+}
+
+fn describe(counter: &counter::Model) -> String {
+    let words: Vec<String> =
+        counter.words.clone().and_then(|words| serde_json::from_value(words).ok()).unwrap_or_default();
+
+    let mut counts = Vec::new();
+    if !words.is_empty() {
+        counts.push(words.join(", "));
+    }
+    if let Some(pattern) = &counter.pattern {
+        counts.push(format!("`{pattern}`"));
+    }
+    let mut line = format!("**{}** counts {}", counter.name, counts.join(" and "));
+    if let Some(channel_id) = counter.channel_id {
+        line.push_str(&format!(" in {}", ChannelId::new(channel_id as u64).mention()));
+    }
+    if let Some(command) = &counter.command {
+        line.push_str(&format!(", shown with /{command}"));
+    }
+    line
+}
+
+/// Checks whether a name is something Discord accepts as a command name.
+fn valid_name(name: &str) -> bool {
+    (1..=MAX_NAME_LENGTH).contains(&name.chars().count())
+        && name.chars().all(|c| c.is_lowercase() || c.is_numeric() || c == '-' || c == '_')
+}
+
+async fn handle_set(ctx: Context, cmd: CommandInteraction, options: &[CommandDataOption]) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+    if !may_manage(&cmd)? {
+        return send_ephemeral_message(ctx, cmd, "You need the Manage Server permission to change this.").await;
+    }
+
+    let Some(name) = name_option(options) else { return send_ephemeral_message(ctx, cmd, "No name passed.").await };
+    if !valid_name(&name) {
+        let error = format!("Names can be up to {MAX_NAME_LENGTH} letters, numbers, dashes and underscores.");
+        return send_ephemeral_message(ctx, cmd, &error).await;
+    }
+
+    let mut words = Vec::new();
+    let mut pattern = None;
+    let mut channel = None;
+    let mut command = None;
+    for option in options {
+        match (option.name.as_str(), &option.value) {
+            ("words", CommandDataOptionValue::String(value)) => {
+                words = value.split(',').flat_map(split_words).collect::<HashSet<_>>().into_iter().collect();
+                words.sort();
+            }
+            ("pattern", CommandDataOptionValue::String(value)) => {
+                // An empty pattern matches every message, which is never what anyone wants
+                pattern = Some(value.trim().to_string()).filter(|pattern| !pattern.is_empty())
+            }
+            ("channel", CommandDataOptionValue::Channel(value)) => channel = Some(*value),
+            ("command", CommandDataOptionValue::String(value)) => command = Some(value.trim().to_lowercase()),
+            _ => {}
+        }
+    }
+
+    if words.is_empty() && pattern.is_none() {
+        return send_ephemeral_message(ctx, cmd, "Pass the words to count, a pattern, or both.").await;
+    }
+    if words.len() > MAX_WORDS {
+        return send_ephemeral_message(ctx, cmd, &format!("A counter can count up to {MAX_WORDS} words.")).await;
+    }
+    if let Some(Err(e)) = pattern.as_deref().map(Regex::new) {
+        return send_ephemeral_message(ctx, cmd, &format!("Could not use that pattern: {e}")).await;
+    }
+    if let Some(command) = &command {
+        if !valid_name(command) {
+            let error = format!("Commands can be up to {MAX_NAME_LENGTH} letters, numbers, dashes and underscores.");
+            return send_ephemeral_message(ctx, cmd, &error).await;
+        }
+        // Don't let it get mixed up with the commands we already have
+        if Command::get_global_commands(&ctx).await?.iter().any(|global| global.name == *command) {
+            return send_ephemeral_message(ctx, cmd, &format!("/{command} is already taken.")).await;
+        }
+    }
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    if let Some(other) = &command {
+        let taken = Counter::find()
+            .filter(counter::Column::ServerId.eq(guild_id.get()))
+            .filter(counter::Column::Command.eq(other.as_str()))
+            .filter(counter::Column::Name.ne(name.as_str()))
+            .one(&db)
+            .await?;
+        if taken.is_some() {
+            return send_ephemeral_message(ctx, cmd, &format!("/{other} already shows another counter.")).await;
+        }
+    }
+
+    let words = (!words.is_empty()).then(|| json!(words));
+    let counter = match find(&db, guild_id, &name).await? {
+        Some(existing) => {
+            // Its old command has to make way if it changed
+            if let Some(old) = existing.command.as_ref().filter(|old| command.as_ref() != Some(*old)) {
+                remove_alias(&ctx, guild_id, old).await?;
+            }
+            let mut active = existing.into_active_model();
+            active.words = Set(words);
+            active.pattern = Set(pattern);
+            active.channel_id = Set(channel.map(|channel| channel.get() as i64));
+            active.command = Set(command);
+            active.update(&db).await?
+        }
+        None => {
+            counter::ActiveModel {
+                id: Default::default(),
+                server_id: Set(guild_id.get() as i64),
+                name: Set(name),
+                words: Set(words),
+                pattern: Set(pattern),
+                channel_id: Set(channel.map(|channel| channel.get() as i64)),
+                command: Set(command),
+            }
+            .insert(&db)
+            .await?
+        }
+    };
+    forget(guild_id);
+    create_alias(&ctx, &counter).await?;
+    info!("{} set counter {} in guild {guild_id}", cmd.user.name, counter.name);
+
+    send_ephemeral_message(ctx, cmd, &describe(&counter)).await
+}
+
+async fn handle_delete(ctx: Context, cmd: CommandInteraction, options: &[CommandDataOption]) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+    if !may_manage(&cmd)? {
+        return send_ephemeral_message(ctx, cmd, "You need the Manage Server permission to change this.").await;
+    }
+    let Some(name) = name_option(options) else { return send_ephemeral_message(ctx, cmd, "No name passed.").await };
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let Some(counter) = find(&db, guild_id, &name).await? else {
+        return send_ephemeral_message(ctx, cmd, "There is no counter with that name.").await;
+    };
+
+    CounterCount::delete_many().filter(counter_count::Column::CounterId.eq(counter.id)).exec(&db).await?;
+    Counter::delete_by_id(counter.id).exec(&db).await?;
+    forget(guild_id);
+    if let Some(command) = &counter.command {
+        remove_alias(&ctx, guild_id, command).await?;
+    }
+    info!("{} removed counter {} in guild {guild_id}", cmd.user.name, counter.name);
+
+    send_ephemeral_message(ctx, cmd, &format!("Removed the {} counter.", counter.name)).await
+}
+
+fn may_manage(cmd: &CommandInteraction) -> Result<bool> {
+    match cmd.member.as_ref().and_then(|m| m.permissions) {
+        Some(permissions) => Ok(permissions.manage_guild()),
+        None => Err(anyhow!("Could not fetch member permissions")),
+    }
+}
+
+/// Suggests the counters of this server.
+pub(super) async fn handle_autocomplete(ctx: Context, cmd: CommandInteraction) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+    let input = cmd.data.autocomplete().map(|option| option.value.trim().to_lowercase()).unwrap_or_default();
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let counters = Counter::find()
+        .filter(counter::Column::ServerId.eq(guild_id.get()))
+        .filter(counter::Column::Name.like(like_pattern("%", &input, "%")))
+        .order_by_asc(counter::Column::Name)
+        .limit(MAX_SUGGESTIONS)
+        .all(&db)
+        .await?;
+
+    let mut response = CreateAutocompleteResponse::new();
+    for counter in counters {
+        response = response.add_string_choice(truncate(&counter.name, MAX_SUGGESTION_LENGTH), counter.name.clone());
+    }
+
+    cmd.create_response(ctx, CreateInteractionResponse::Autocomplete(response)).await?;
+    Ok(())
+}
+
+/// Forgets the counters of a guild the bot got removed from.
+pub(crate) async fn purge(db: &DatabaseConnection, guild_id: GuildId) -> Result<()> {
+    let ids: Vec<i64> = Counter::find()
+        .select_only()
+        .column(counter::Column::Id)
+        .filter(counter::Column::ServerId.eq(guild_id.get()))
+        .into_tuple()
+        .all(db)
+        .await?;
+    CounterCount::delete_many().filter(counter_count::Column::CounterId.is_in(ids)).exec(db).await?;
+    Counter::delete_many().filter(counter::Column::ServerId.eq(guild_id.get())).exec(db).await?;
+    forget(guild_id);
+    Ok(())
+}
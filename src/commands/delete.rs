@@ -18,11 +18,14 @@ pub(super) async fn register(ctx: &Context) -> Result<()> {
             .description("Deletes a specific quote")
             .default_member_permissions(Permissions::MANAGE_MESSAGES)
             .dm_permission(false)
-            .add_option(CreateCommandOption::new(
-                CommandOptionType::Integer,
-                "id",
-                "A quote id (found in the bottom of the quote)",
-            )),
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "id",
+                    "A quote id (found in the bottom of the quote)",
+                )
+                .set_autocomplete(true),
+            ),
     )
     .await?;
     Ok(())
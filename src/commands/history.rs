@@ -0,0 +1,353 @@
+use anyhow::{anyhow, Result};
+use chrono::{FixedOffset, Utc};
+use sea_orm::{
+    sea_query::{Expr, ExprTrait, Func},
+    ActiveModelTrait,
+    ActiveValue::Set,
+    ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, QueryOrder, QuerySelect,
+};
+use serenity::{
+    all::{
+        ChannelType, Command, CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+        CreateInteractionResponseMessage,
+    },
+    builder::{CreateCommand, CreateCommandOption, CreateEmbed, CreateInteractionResponse, EditInteractionResponse},
+    cache::GuildRef,
+    client::Context,
+    model::{
+        guild::Member,
+        id::{ChannelId, MessageId},
+        Colour,
+    },
+    prelude::Mentionable,
+};
+
+use entity::{
+    archive_channel, archived_message,
+    prelude::{ArchiveChannel, ArchivedMessage},
+};
+
+use crate::{
+    archive,
+    commands::{edit_interaction, send_ephemeral_message},
+    quote::{like_pattern, truncate},
+    util::DatabaseTypeMapKey,
+};
+
+const DEFAULT_RETENTION_DAYS: i32 = 30;
+const MAX_RETENTION_DAYS: i32 = 365;
+const DEFAULT_BACKFILL: usize = 1000;
+const MAX_BACKFILL: usize = 10000;
+// How many results a search shows, and how much of every message
+const MAX_RESULTS: u64 = 10;
+const MAX_RESULT_LENGTH: usize = 200;
+
+pub(super) async fn register(ctx: &Context) -> Result<()> {
+    Command::create_global_command(
+        ctx,
+        CreateCommand::new("history")
+            .description("Searches the archived messages of this server")
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "search", "Finds archived messages")
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::String, "query", "What the message says")
+                            .required(true)
+                            .max_length(100),
+                    )
+                    .add_sub_option(CreateCommandOption::new(
+                        CommandOptionType::User,
+                        "user",
+                        "Only messages by this member",
+                    ))
+                    .add_sub_option(channel_option("Only messages in this channel")),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "enable",
+                    "Starts archiving the messages of a channel",
+                )
+                .add_sub_option(channel_option("The channel to archive, this one if left empty"))
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Integer,
+                        "retention",
+                        "How many days messages are kept",
+                    )
+                    .min_int_value(1)
+                    .max_int_value(MAX_RETENTION_DAYS as u64),
+                ),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "disable",
+                    "Stops archiving a channel, and removes what was archived",
+                )
+                .add_sub_option(channel_option("The channel to stop archiving, this one if left empty")),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "backfill",
+                    "Archives the messages that were posted before archiving was enabled",
+                )
+                .add_sub_option(channel_option("The channel to backfill, this one if left empty"))
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Integer, "count", "How many messages to read at most")
+                        .min_int_value(1)
+                        .max_int_value(MAX_BACKFILL as u64),
+                ),
+            ),
+    )
+    .await?;
+    Ok(())
+}
+
+fn channel_option(description: &str) -> CreateCommandOption {
+    CreateCommandOption::new(CommandOptionType::Channel, "channel", description).channel_types(vec![
+        ChannelType::Text,
+        ChannelType::News,
+        ChannelType::PublicThread,
+        ChannelType::PrivateThread,
+        ChannelType::NewsThread,
+    ])
+}
+
+pub(super) async fn handle_command(ctx: Context, cmd: CommandInteraction) -> Result<()> {
+    let Some((subcommand, options)) = cmd.data.options.first().and_then(|option| match &option.value {
+        CommandDataOptionValue::SubCommand(options) => Some((option.name.clone(), options.clone())),
+        _ => None,
+    }) else {
+        return send_ephemeral_message(ctx, cmd, "No subcommand passed").await;
+    };
+
+    if subcommand != "search" {
+        let permissions = match cmd.member.as_ref().and_then(|m| m.permissions) {
+            Some(p) => p,
+            None => return Err(anyhow!("Could not fetch member permissions")),
+        };
+        if !permissions.manage_guild() {
+            return send_ephemeral_message(ctx, cmd, "You need the Manage Server permission to change this.").await;
+        }
+    }
+
+    let channel_id = options.iter().find_map(|option| match (option.name.as_str(), &option.value) {
+        ("channel", CommandDataOptionValue::Channel(channel)) => Some(*channel),
+        _ => None,
+    });
+    // Managing the archive goes for the current channel, unless told otherwise
+    let target = channel_id.unwrap_or(cmd.channel_id);
+
+    match subcommand.as_str() {
+        "search" => handle_search(ctx, cmd, channel_id, &options).await,
+        "enable" => handle_enable(ctx, cmd, target, &options).await,
+        "disable" => handle_disable(ctx, cmd, target).await,
+        "backfill" => handle_backfill(ctx, cmd, target, &options).await,
+        _ => send_ephemeral_message(ctx, cmd, "Unknown subcommand").await,
+    }
+}
+
+async fn handle_search(
+    ctx: Context,
+    cmd: CommandInteraction,
+    channel_id: Option<ChannelId>,
+    options: &[CommandDataOption],
+) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+    let Some(member) = cmd.member.as_deref() else { return Err(anyhow!("Could not fetch member")) };
+
+    let mut query = String::new();
+    let mut user = None;
+    for option in options {
+        match (option.name.as_str(), &option.value) {
+            ("query", CommandDataOptionValue::String(value)) => query = value.trim().to_lowercase(),
+            ("user", CommandDataOptionValue::User(value)) => user = Some(*value),
+            _ => {}
+        }
+    }
+    if query.is_empty() {
+        return send_ephemeral_message(ctx, cmd, "You need to search for something.").await;
+    }
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let mut channels = ArchiveChannel::find().filter(archive_channel::Column::ServerId.eq(guild_id.get()));
+    if let Some(channel_id) = channel_id {
+        channels = channels.filter(archive_channel::Column::ChannelId.eq(channel_id.get()));
+    }
+    let channels = channels.all(&db).await?;
+
+    // Only search the channels the member can see
+    let visible: Vec<i64> = {
+        let Some(guild) = ctx.cache.guild(guild_id) else { return Err(anyhow!("Guild {guild_id} is not cached")) };
+        channels
+            .iter()
+            .map(|channel| channel.channel_id)
+            .filter(|channel_id| can_view(&guild, member, ChannelId::new(*channel_id as u64)))
+            .collect()
+    };
+    if visible.is_empty() {
+        let content = match channel_id {
+            Some(channel_id) => format!("{} is not being archived.", channel_id.mention()),
+            None => "This server doesn't archive any channels you can see.".to_string(),
+        };
+        return send_ephemeral_message(ctx, cmd, &content).await;
+    }
+
+    // Whatever is searched for is taken literally
+    let pattern = like_pattern("%", &query, "%");
+    let mut results = ArchivedMessage::find()
+        .filter(archived_message::Column::ChannelId.is_in(visible))
+        .filter(Func::lower(Expr::col((archived_message::Entity, archived_message::Column::Content))).like(pattern));
+    if let Some(user) = user {
+        results = results.filter(archived_message::Column::AuthorId.eq(user.get()));
+    }
+    let results = results.order_by_desc(archived_message::Column::Timestamp).limit(MAX_RESULTS).all(&db).await?;
+
+    if results.is_empty() {
+        return send_ephemeral_message(ctx, cmd, &format!("Nothing in the archive mentions \"{query}\".")).await;
+    }
+
+    let lines: Vec<String> = results
+        .iter()
+        .map(|result| {
+            let channel_id = ChannelId::new(result.channel_id as u64);
+            format!(
+                "<t:{}:d> **{}** in {}: {} [Jump]({})",
+                result.timestamp.timestamp(),
+                result.author,
+                channel_id.mention(),
+                truncate(&result.content.replace('\n', " "), MAX_RESULT_LENGTH),
+                MessageId::new(result.message_id as u64).link(channel_id, Some(guild_id))
+            )
+        })
+        .collect();
+    let embed = CreateEmbed::new()
+        .title(format!("Messages mentioning \"{}\"", truncate(&query, 50)))
+        .colour(Colour::FABLED_PINK)
+        .description(lines.join("\n"));
+
+    cmd.create_response(
+        &ctx,
+        CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().ephemeral(true).embed(embed)),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Whether a member can read a channel, threads going by their parent channel.
+fn can_view(guild: &GuildRef, member: &Member, channel_id: ChannelId) -> bool {
+    let channel = guild
+        .channels
+        .get(&channel_id)
+        .or_else(|| guild.threads.iter().find(|thread| thread.id == channel_id))
+        .and_then(|channel| match channel.parent_id {
+            Some(parent_id) if channel.thread_metadata.is_some() => guild.channels.get(&parent_id),
+            _ => Some(channel),
+        });
+    channel.is_some_and(|channel| guild.user_permissions_in(channel, member).view_channel())
+}
+
+async fn handle_enable(
+    ctx: Context,
+    cmd: CommandInteraction,
+    channel_id: ChannelId,
+    options: &[CommandDataOption],
+) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+    let retention = options.iter().find_map(|option| match (option.name.as_str(), &option.value) {
+        ("retention", CommandDataOptionValue::Integer(days)) => Some(*days as i32),
+        _ => None,
+    });
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let content = match archive::channel(&db, channel_id).await? {
+        Some(channel) => {
+            let mut channel = channel.into_active_model();
+            if let Some(retention) = retention {
+                channel.retention_days = Set(retention);
+            }
+            let channel = channel.update(&db).await?;
+            format!(
+                "{} is already being archived, messages are kept for {} days.",
+                channel_id.mention(),
+                channel.retention_days
+            )
+        }
+        None => {
+            let channel = archive_channel::ActiveModel {
+                channel_id: Set(channel_id.get() as i64),
+                server_id: Set(guild_id.get() as i64),
+                retention_days: Set(retention.unwrap_or(DEFAULT_RETENTION_DAYS)),
+                covered_since: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
+            }
+            .insert(&db)
+            .await?;
+            format!(
+                "Now archiving {}, messages are kept for {} days. Use `/history backfill` to archive what was posted before.",
+                channel_id.mention(),
+                channel.retention_days
+            )
+        }
+    };
+    send_ephemeral_message(ctx, cmd, &content).await
+}
+
+async fn handle_disable(ctx: Context, cmd: CommandInteraction, channel_id: ChannelId) -> Result<()> {
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    if archive::channel(&db, channel_id).await?.is_none() {
+        return send_ephemeral_message(ctx, cmd, &format!("{} is not being archived.", channel_id.mention())).await;
+    }
+
+    ArchiveChannel::delete_by_id(channel_id.get() as i64).exec(&db).await?;
+    let deleted = ArchivedMessage::delete_many()
+        .filter(archived_message::Column::ChannelId.eq(channel_id.get()))
+        .exec(&db)
+        .await?;
+    let content = format!(
+        "Stopped archiving {}, and removed the {} messages that were archived.",
+        channel_id.mention(),
+        deleted.rows_affected
+    );
+    send_ephemeral_message(ctx, cmd, &content).await
+}
+
+async fn handle_backfill(
+    ctx: Context,
+    cmd: CommandInteraction,
+    channel_id: ChannelId,
+    options: &[CommandDataOption],
+) -> Result<()> {
+    let count = options
+        .iter()
+        .find_map(|option| match (option.name.as_str(), &option.value) {
+            ("count", CommandDataOptionValue::Integer(count)) => Some(*count as usize),
+            _ => None,
+        })
+        .unwrap_or(DEFAULT_BACKFILL);
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let Some(channel) = archive::channel(&db, channel_id).await? else {
+        let content = format!("{} is not being archived, use `/history enable` first.", channel_id.mention());
+        return send_ephemeral_message(ctx, cmd, &content).await;
+    };
+
+    // Reading the history takes a while, as Discord only hands out 100 messages at a time
+    cmd.create_response(
+        &ctx,
+        CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new().ephemeral(true)),
+    )
+    .await?;
+
+    match archive::backfill(&ctx, &channel, count).await {
+        Ok(read) => {
+            let content = format!("Read {read} messages of {} into the archive.", channel_id.mention());
+            edit_interaction(ctx, cmd, &content).await
+        }
+        Err(e) => {
+            cmd.edit_response(&ctx, EditInteractionResponse::new().content("Could not backfill the archive.")).await?;
+            Err(e)
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+    time::Instant,
+};
+
+use anyhow::Result;
+use sea_orm::{sea_query::Expr, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use serenity::{
+    all::{Command, CommandDataOptionValue, CommandInteraction, CommandOptionType},
+    builder::{
+        CreateCommand, CreateCommandOption, CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter,
+        CreateInteractionResponse, CreateInteractionResponseMessage,
+    },
+    client::Context,
+    model::{
+        id::{GuildId, UserId},
+        Colour,
+    },
+};
+use tokio::sync::Mutex;
+
+use entity::{prelude::Quote, quote};
+
+use crate::{
+    commands::send_ephemeral_message,
+    util::{markov::MarkovChain, DatabaseTypeMapKey},
+};
+
+// How often we try to come up with a sentence that isn't just a copy of an existing quote
+const GENERATE_ATTEMPTS: usize = 10;
+// How many trained models we keep around, the least recently used one makes way for a new one
+const MAX_CACHED_MODELS: usize = 100;
+
+static MODEL_CACHE: OnceLock<Mutex<HashMap<(GuildId, UserId), CachedModel>>> = OnceLock::new();
+
+#[derive(Clone)]
+struct CachedModel {
+    // The amount of quotes and the newest quote id at the time of training, used to detect new quotes
+    quote_count: i64,
+    newest_quote: Option<i64>,
+    author: String,
+    quotes: Arc<Vec<String>>,
+    chain: Arc<MarkovChain>,
+    last_used: Instant,
+}
+
+pub(super) async fn register(ctx: &Context) -> Result<()> {
+    Command::create_global_command(
+        ctx,
+        CreateCommand::new("impersonate")
+            .description("Makes up a sentence in the style of the specified user, based on their quotes")
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::User, "user", "The user to impersonate").required(true),
+            ),
+    )
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn handle_command(ctx: Context, cmd: CommandInteraction) -> Result<()> {
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let Some(guild_id) = cmd.guild_id else {
+        return send_ephemeral_message(ctx, cmd, "This command can only be used in servers.").await;
+    };
+    let user = match cmd.data.options.first().map(|id| &id.value) {
+        Some(CommandDataOptionValue::User(user)) => *user,
+        _ => return send_ephemeral_message(ctx, cmd, "No user received").await,
+    };
+
+    // Check how many quotes this user has, so we know whether our cached model is still up to date
+    let (quote_count, newest_quote): (i64, Option<i64>) = Quote::find()
+        .select_only()
+        .column_as(Expr::col(quote::Column::Id).count(), "count")
+        .column_as(Expr::col(quote::Column::Id).max(), "newest")
+        .filter(quote::Column::ServerId.eq(guild_id.get()))
+        .filter(quote::Column::AuthorId.eq(user.get()))
+        .into_tuple()
+        .one(&db)
+        .await?
+        .unwrap_or_default();
+    if quote_count == 0 {
+        return send_ephemeral_message(ctx, cmd, "I don't know any quotes by that user, so I can't impersonate them.")
+            .await;
+    }
+
+    let cached = MODEL_CACHE.get_or_init(Default::default).lock().await.get_mut(&(guild_id, user)).map(|model| {
+        model.last_used = Instant::now();
+        model.clone()
+    });
+    let model = match cached {
+        Some(model) if model.quote_count == quote_count && model.newest_quote == newest_quote => model,
+        _ => {
+            // New quotes arrived (or some got deleted), so we retrain the model
+            let quotes: Vec<(String, String)> = Quote::find()
+                .select_only()
+                .column(quote::Column::Author)
+                .column(quote::Column::Text)
+                .filter(quote::Column::ServerId.eq(guild_id.get()))
+                .filter(quote::Column::AuthorId.eq(user.get()))
+                .order_by_asc(quote::Column::Id)
+                .into_tuple()
+                .all(&db)
+                .await?;
+
+            let author = quotes.last().map(|(author, _)| author.clone()).unwrap_or_default();
+            let quotes: Vec<String> = quotes.into_iter().map(|(_, text)| text).collect();
+            let model = CachedModel {
+                quote_count,
+                newest_quote,
+                author,
+                chain: Arc::new(MarkovChain::train(quotes.iter().map(String::as_str))),
+                quotes: Arc::new(quotes),
+                last_used: Instant::now(),
+            };
+            let mut cache = MODEL_CACHE.get_or_init(Default::default).lock().await;
+            if cache.len() >= MAX_CACHED_MODELS && !cache.contains_key(&(guild_id, user)) {
+                let oldest = cache.iter().min_by_key(|(_, model)| model.last_used).map(|(key, _)| *key);
+                if let Some(oldest) = oldest {
+                    cache.remove(&oldest);
+                }
+            }
+            cache.insert((guild_id, user), model.clone());
+            model
+        }
+    };
+
+    if model.chain.is_empty() {
+        return send_ephemeral_message(
+            ctx,
+            cmd,
+            "That user only has quotes without text, so I can't impersonate them.",
+        )
+        .await;
+    }
+
+    // Prefer a sentence that is actually new, but settle for a copy if that's all the chain can do
+    let mut sentence = model.chain.generate();
+    for _ in 1..GENERATE_ATTEMPTS {
+        if !model.quotes.contains(&sentence) {
+            break;
+        }
+        sentence = model.chain.generate();
+    }
+
+    let mut author = CreateEmbedAuthor::new(model.author);
+    if let Ok(user) = user.to_user(&ctx).await {
+        author = author.icon_url(user.face());
+    }
+    let plural = if quote_count == 1 { "quote" } else { "quotes" };
+    let embed = CreateEmbed::new()
+        .author(author)
+        .description(format!("*\"{sentence}\"*"))
+        .footer(CreateEmbedFooter::new(format!("Impersonation based on {quote_count} {plural}")))
+        .colour(Colour::FABLED_PINK);
+
+    cmd.create_response(
+        ctx,
+        CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().add_embed(embed)),
+    )
+    .await?;
+    Ok(())
+}
@@ -1,18 +1,19 @@
-use anyhow::{anyhow, Result};
-use rand::{rng, seq::IndexedRandom};
-use sea_orm::{
-    sea_query::{Expr, ExprTrait, Func},
-    ColumnTrait, EntityTrait, QueryFilter, QuerySelect,
-};
+use anyhow::Result;
 use serenity::{
     all::{Command, CommandDataOptionValue, CommandInteraction, CommandOptionType},
-    builder::{CreateCommand, CreateCommandOption},
+    builder::{CreateAutocompleteResponse, CreateCommand, CreateCommandOption, CreateInteractionResponse},
     client::Context,
 };
 
-use entity::{prelude::Quote, quote};
-
-use crate::{commands::send_ephemeral_message, quote::post_quote, util::DatabaseTypeMapKey};
+use crate::{
+    commands::{
+        quote::{MAX_SUGGESTIONS, MAX_SUGGESTION_LENGTH},
+        rquote::post_random_quote,
+        send_ephemeral_message,
+    },
+    quote::{find_suggestions, truncate, QuoteFilter},
+    util::DatabaseTypeMapKey,
+};
 
 pub(super) async fn register(ctx: &Context) -> Result<()> {
     Command::create_global_command(
@@ -22,7 +23,8 @@ pub(super) async fn register(ctx: &Context) -> Result<()> {
             .dm_permission(false)
             .add_option(
                 CreateCommandOption::new(CommandOptionType::String, "keyword", "The keyword to search for.")
-                    .required(true),
+                    .required(true)
+                    .set_autocomplete(true),
             ),
     )
     .await?;
@@ -30,34 +32,45 @@ pub(super) async fn register(ctx: &Context) -> Result<()> {
 }
 
 pub(super) async fn handle_command(ctx: Context, cmd: CommandInteraction) -> Result<()> {
-    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
-    let Some(guild_id) = cmd.guild_id else {
-        return send_ephemeral_message(ctx, cmd, "This command can only be used in servers.").await;
-    };
     let keyword = match cmd.data.options.first().map(|id| &id.value) {
-        Some(CommandDataOptionValue::String(keyword)) => keyword,
+        Some(CommandDataOptionValue::String(keyword)) => keyword.to_owned(),
         _ => return send_ephemeral_message(ctx, cmd, "No keyword received").await,
     };
 
-    let ids: Vec<i64> = Quote::find()
-        .select_only()
-        .column(quote::Column::Id)
-        .filter(quote::Column::ServerId.eq(guild_id.get()))
-        .filter(
-            Func::lower(Expr::col((quote::Entity, quote::Column::Text))).like(format!("%{}%", keyword.to_lowercase())),
-        )
-        .into_tuple()
-        .all(&db)
-        .await?;
-    let Some(chosen_random) = ids.choose(&mut rng()) else {
-        return send_ephemeral_message(ctx, cmd, "Could not find any random quotes for that keyword, do none exist?")
-            .await;
-    };
+    let filter = QuoteFilter { keyword: Some(keyword), ..Default::default() };
+    post_random_quote(ctx, cmd, filter, "Could not find any random quotes for that keyword, do none exist?").await
+}
 
-    let quote = Quote::find_by_id(*chosen_random).one(&db).await?;
+/// Suggests snippets of quotes containing the keyword typed so far, starting at the keyword.
+/// Since every snippet is a literal piece of a quote, choosing one is guaranteed to find at least that quote.
+pub(super) async fn handle_autocomplete(ctx: Context, cmd: CommandInteraction) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+    let input = cmd.data.autocomplete().map(|option| option.value.trim().to_lowercase()).unwrap_or_default();
 
-    match quote {
-        Some(quote) => post_quote(&ctx, quote, cmd.channel_id, Some(cmd)).await,
-        None => Err(anyhow!("Selected random keyword quote that ended up not existing")),
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let suggestions = find_suggestions(&db, guild_id, &input, MAX_SUGGESTIONS).await?;
+
+    let mut snippets: Vec<String> = Vec::new();
+    for (_, _, text) in suggestions {
+        // Lowercasing can change the length of some characters, so we look for the keyword in the text itself
+        let Some(start) = text
+            .char_indices()
+            .map(|(i, _)| i)
+            .find(|&i| text[i..].chars().flat_map(char::to_lowercase).take(input.chars().count()).eq(input.chars()))
+        else {
+            continue;
+        };
+        let snippet = text[start..].chars().take(MAX_SUGGESTION_LENGTH).collect::<String>().trim().to_string();
+        if !snippet.is_empty() && !snippets.contains(&snippet) {
+            snippets.push(snippet);
+        }
+    }
+
+    let mut response = CreateAutocompleteResponse::new();
+    for snippet in snippets {
+        response = response.add_string_choice(truncate(&snippet, MAX_SUGGESTION_LENGTH), snippet);
     }
+
+    cmd.create_response(ctx, CreateInteractionResponse::Autocomplete(response)).await?;
+    Ok(())
 }
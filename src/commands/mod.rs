@@ -5,22 +5,33 @@ use serenity::{
     client::Context,
 };
 
-pub(crate) use ccounter::handle_ingress as handle_ccounter_ingress;
+pub(crate) use counter::handle_ingress as handle_counter_ingress;
+pub(crate) use counter::purge as counter_purge;
 pub(crate) use mia::press_loop as mia_press_loop;
+pub(crate) use readycheck::button::press_loop as readycheck_press_loop;
+pub(crate) use readycheck::config::forget as readycheck_forget_config;
+pub(crate) use readycheck::lifecycle::resume as readycheck_resume;
+pub(crate) use readycheck::voice::voice_state_update as readycheck_voice_state_update;
 pub(crate) use rolebuttons::button::press_loop as rolebutton_press_loop;
 pub(crate) use rolebuttons::post::check_for_update as rolebutton_post_check_for_update;
+pub(crate) use tldr::cache::press_loop as tldr_refresh_press_loop;
+pub(crate) use tldr::digest::run as tldr_digest_run;
+pub(crate) use tldr::limits::forget as tldr_forget_limits;
+pub(crate) use tldr::prompts::forget as tldr_forget_prompts;
 
 use crate::handler::Handler;
 
-mod ccounter;
+mod counter;
 mod cquote;
 mod delete;
+mod history;
+mod impersonate;
 mod kwquote;
 mod lamia;
 mod mia;
 mod purge;
 mod quote;
-mod readycheck;
+pub(crate) mod readycheck;
 mod rolebuttons;
 mod rquote;
 pub(crate) mod tldr;
@@ -28,9 +39,11 @@ mod uquote;
 mod voicequote;
 
 pub(crate) async fn introduce_commands(ctx: &Context) -> Result<()> {
-    ccounter::register(ctx).await?;
+    counter::register(ctx).await?;
     cquote::register(ctx).await?;
     delete::register(ctx).await?;
+    history::register(ctx).await?;
+    impersonate::register(ctx).await?;
     kwquote::register(ctx).await?;
     purge::register(ctx).await?;
     quote::register(ctx).await?;
@@ -40,6 +53,7 @@ pub(crate) async fn introduce_commands(ctx: &Context) -> Result<()> {
     rolebuttons::register(ctx).await?;
     rquote::register(ctx).await?;
     tldr::register(ctx).await?;
+    tldr::ask::register(ctx).await?;
     uquote::register(ctx).await?;
     voicequote::register(ctx).await?;
     Ok(())
@@ -49,11 +63,13 @@ pub(crate) async fn handle_command(handler: &Handler, ctx: Context, cmd: Command
     info!("Received command from {}: /{} {}", cmd.user.name, cmd.data.name, unwrap_options(&cmd.data.options, true));
 
     match cmd.data.name.as_str() {
-        "cum" => ccounter::handle_command(ctx, cmd).await,
+        "counter" => counter::handle_command(ctx, cmd).await,
         "cquote" => cquote::handle_command(ctx, cmd).await,
         "delete" => delete::handle_command(ctx, cmd).await,
+        "history" => history::handle_command(ctx, cmd).await,
+        "impersonate" => impersonate::handle_command(ctx, cmd).await,
         "kwquote" => kwquote::handle_command(ctx, cmd).await,
-        "purge" => purge::handle_command(ctx, cmd).await,
+        "purge" => purge::handle_command(handler, ctx, cmd).await,
         "quote" => quote::handle_command(ctx, cmd).await,
         "days_since_lamia_horny" => lamia::handle_command(ctx, cmd).await,
         "mia" => mia::handle_command(ctx, cmd).await,
@@ -61,13 +77,26 @@ pub(crate) async fn handle_command(handler: &Handler, ctx: Context, cmd: Command
         "rolebuttons" => rolebuttons::handle_command(ctx, cmd).await,
         "rquote" => rquote::handle_command(ctx, cmd).await,
         "tldr" => tldr::handle_command(ctx, cmd).await,
+        "ask" => tldr::ask::handle_command(ctx, cmd).await,
         "uquote" => uquote::handle_command(ctx, cmd).await,
         "voicequote" => voicequote::handle_command(ctx, cmd).await,
-        _ => return Err(anyhow!("Unknown command received: {}", cmd.data.name)),
+        // Counters can have commands of their own in their guild
+        _ => counter::handle_alias(ctx, cmd).await,
     }?;
     Ok(())
 }
 
+pub(crate) async fn handle_autocomplete(ctx: Context, cmd: CommandInteraction) -> Result<()> {
+    match cmd.data.name.as_str() {
+        "counter" => counter::handle_autocomplete(ctx, cmd).await,
+        "delete" | "quote" => quote::handle_autocomplete(ctx, cmd).await,
+        "kwquote" | "rquote" => kwquote::handle_autocomplete(ctx, cmd).await,
+        "purge" => purge::handle_autocomplete(ctx, cmd).await,
+        "readycheck" => readycheck::handle_autocomplete(ctx, cmd).await,
+        _ => Err(anyhow!("Unknown autocomplete received: {}", cmd.data.name)),
+    }
+}
+
 async fn send_ephemeral_message(ctx: Context, cmd: CommandInteraction, error: &str) -> Result<()> {
     Ok(cmd
         .create_response(
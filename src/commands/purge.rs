@@ -1,33 +1,119 @@
+use std::time::Duration;
+
 use anyhow::{anyhow, Result};
-use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use chrono::Utc;
+use sea_orm::{
+    prelude::DateTimeWithTimeZone,
+    sea_query::{Expr, ExprTrait, Func},
+    ActiveModelTrait,
+    ActiveValue::Set,
+    ColumnTrait, EntityTrait, FromQueryResult, QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
+};
+use serde::Serialize;
 use serenity::{
-    all::{Command, CommandDataOptionValue, CommandInteraction, CommandOptionType},
-    builder::{CreateCommand, CreateCommandOption},
+    all::{
+        ButtonStyle, Command, CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+        ComponentInteraction,
+    },
+    builder::{
+        CreateActionRow, CreateAutocompleteResponse, CreateButton, CreateCommand, CreateCommandOption,
+        CreateInteractionResponse, CreateInteractionResponseMessage, EditInteractionResponse,
+    },
     client::Context,
-    model::permissions::Permissions,
+    model::{id::ChannelId, permissions::Permissions},
+};
+use tokio::{
+    select,
+    time::{sleep_until, Instant},
+};
+
+use entity::{prelude::Quote, quote, quote_purge};
+
+use crate::{
+    commands::{
+        quote::{MAX_SUGGESTIONS, MAX_SUGGESTION_LENGTH},
+        send_ephemeral_message,
+    },
+    handler::Handler,
+    quote::{like_pattern, truncate, QuoteFilter},
+    util::{parse_date, DatabaseTypeMapKey},
 };
 
-use entity::{prelude::Quote, quote};
+// How long the admin has to confirm a purge before we give up on it
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(60);
 
-use crate::{commands::send_ephemeral_message, util::DatabaseTypeMapKey};
+/// What we remember of every quote that got purged, so a purge can always be looked back on.
+#[derive(Serialize, FromQueryResult)]
+struct PurgedQuote {
+    id: i64,
+    author_id: i64,
+    author: String,
+    channel_id: i64,
+    channel_name: String,
+    text: String,
+    timestamp: DateTimeWithTimeZone,
+}
 
 pub(super) async fn register(ctx: &Context) -> Result<()> {
     Command::create_global_command(
         ctx,
         CreateCommand::new("purge")
-            .description("Purges all quotes by the specified user.")
+            .description("Purges quotes in bulk")
             .default_member_permissions(Permissions::MANAGE_MESSAGES)
             .dm_permission(false)
             .add_option(
-                CreateCommandOption::new(CommandOptionType::User, "user", "The user to purge all quotes from")
-                    .required(true),
+                CreateCommandOption::new(CommandOptionType::SubCommand, "user", "Purges all quotes by a user")
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::User, "user", "The user to purge all quotes from")
+                            .required(true),
+                    ),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "channel",
+                    "Purges all quotes from a channel, including channels that no longer exist",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "channel",
+                        "The channel (or channel id) to purge all quotes from",
+                    )
+                    .required(true)
+                    .set_autocomplete(true),
+                ),
+            )
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "dates", "Purges all quotes in a date range")
+                    .add_sub_option(CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "after",
+                        "Purge quotes from this date (YYYY-MM-DD) onwards",
+                    ))
+                    .add_sub_option(CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "before",
+                        "Purge quotes from before this date (YYYY-MM-DD)",
+                    )),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "search",
+                    "Purges all quotes containing a search query",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "query", "The text to search for")
+                        .required(true),
+                ),
             ),
     )
     .await?;
     Ok(())
 }
 
-pub(super) async fn handle_command(ctx: Context, cmd: CommandInteraction) -> Result<()> {
+pub(super) async fn handle_command(handler: &Handler, ctx: Context, cmd: CommandInteraction) -> Result<()> {
     let Some(guild_id) = cmd.guild_id else {
         return send_ephemeral_message(ctx, cmd, "This command can only be used in servers.").await;
     };
@@ -40,19 +126,189 @@ pub(super) async fn handle_command(ctx: Context, cmd: CommandInteraction) -> Res
         return send_ephemeral_message(ctx, cmd, "You do not have permission to use this command.").await;
     }
 
-    let user_id = match cmd.data.options.first().map(|id| &id.value) {
-        Some(CommandDataOptionValue::User(user)) => user,
-        _ => return send_ephemeral_message(ctx, cmd, "No user received, which is needed for deletion.").await,
+    let filter = match parse_filter(cmd.data.options.first()) {
+        Ok(filter) => filter,
+        Err(e) => return send_ephemeral_message(ctx, cmd, e).await,
     };
-    let user_name =
-        user_id.to_user(&ctx).await.ok().map(|u| u.name).unwrap_or_else(|| "<Can't fetch user>".to_string());
+    let criteria = filter.describe();
 
+    // Do a dry run first, so the admin knows exactly what they're about to remove
     let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let matching: Vec<PurgedQuote> = filter
+        .apply(
+            guild_id,
+            Quote::find()
+                .select_only()
+                .column(quote::Column::Id)
+                .column(quote::Column::AuthorId)
+                .column(quote::Column::Author)
+                .column(quote::Column::ChannelId)
+                .column(quote::Column::ChannelName)
+                .column(quote::Column::Text)
+                .column(quote::Column::Timestamp),
+        )
+        .order_by_asc(quote::Column::Id)
+        .into_model()
+        .all(&db)
+        .await?;
+    if matching.is_empty() {
+        return send_ephemeral_message(ctx, cmd, &format!("There are no quotes {criteria}.")).await;
+    }
+
+    let confirm_id = format!("purge_{}_confirm", cmd.id);
+    let cancel_id = format!("purge_{}_cancel", cmd.id);
+    let mut recv = handler.subscribe_to_component_interactions();
+
+    let plural = if matching.len() == 1 { "quote" } else { "quotes" };
+    cmd.create_response(
+        &ctx,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .ephemeral(true)
+                .content(format!(
+                    "This will delete **{}** {plural} {criteria}. This can not be undone, are you sure?",
+                    matching.len()
+                ))
+                .components(vec![CreateActionRow::Buttons(vec![
+                    CreateButton::new(confirm_id.as_str()).label("Delete them").style(ButtonStyle::Danger),
+                    CreateButton::new(cancel_id.as_str()).label("Cancel").style(ButtonStyle::Secondary),
+                ])]),
+        ),
+    )
+    .await?;
+
+    let end_time = Instant::now() + CONFIRMATION_TIMEOUT;
+    let interaction = loop {
+        let (_, interaction): (Context, ComponentInteraction) = select! {
+            interaction = recv.recv() => {
+                match interaction {
+                    Ok(interaction) => interaction,
+                    Err(e) => {
+                        error!("Error receiving interaction in purge confirmation loop: {e}");
+                        continue;
+                    }
+                }
+            },
+            _ = sleep_until(end_time) => {
+                cmd.edit_response(
+                    &ctx,
+                    EditInteractionResponse::new().content("Purge confirmation expired.").components(vec![]),
+                )
+                .await?;
+                return Ok(());
+            }
+        };
+
+        if interaction.data.custom_id == confirm_id || interaction.data.custom_id == cancel_id {
+            break interaction;
+        }
+    };
+
+    if interaction.data.custom_id == cancel_id {
+        return respond(&ctx, &interaction, "Purge cancelled, nothing was deleted.").await;
+    }
+
+    // Delete exactly what was confirmed, even if more matching quotes came in while we waited.
+    // The audit entry goes in the same transaction, so quotes are never gone without a record of them.
+    let ids: Vec<i64> = matching.iter().map(|quote| quote.id).collect();
+    let txn = db.begin().await?;
+    let deleted = Quote::delete_many()
+        .filter(quote::Column::Id.is_in(ids))
+        .filter(quote::Column::ServerId.eq(guild_id.get()))
+        .exec(&txn)
+        .await?
+        .rows_affected;
 
-    Quote::delete_many()
-        .filter(quote::Column::AuthorId.eq(user_id.get()))
+    quote_purge::ActiveModel {
+        id: Default::default(),
+        server_id: Set(guild_id.get() as i64),
+        purged_by: Set(cmd.user.id.get() as i64),
+        criteria: Set(criteria.clone()),
+        removed: Set(serde_json::to_value(&matching)?),
+        timestamp: Set(Utc::now().fixed_offset()),
+    }
+    .insert(&txn)
+    .await?;
+    txn.commit().await?;
+    info!("{} purged {deleted} quotes {criteria} in guild {guild_id}", cmd.user.name);
+
+    let plural = if deleted == 1 { "quote" } else { "quotes" };
+    respond(&ctx, &interaction, &format!("Deleted {deleted} {plural} {criteria}.")).await
+}
+
+fn parse_filter(subcommand: Option<&CommandDataOption>) -> Result<QuoteFilter, &'static str> {
+    let Some(CommandDataOptionValue::SubCommand(options)) = subcommand.map(|o| &o.value) else {
+        return Err("No subcommand passed");
+    };
+
+    let mut filter = QuoteFilter::default();
+    for option in options {
+        match (option.name.as_str(), &option.value) {
+            ("user", CommandDataOptionValue::User(user)) => filter.user = Some(*user),
+            ("channel", CommandDataOptionValue::String(channel)) => {
+                let id = channel.trim().trim_start_matches("<#").trim_end_matches('>');
+                filter.channel = Some(id.parse::<ChannelId>().map_err(|_| "Could not parse that channel.")?);
+            }
+            ("query", CommandDataOptionValue::String(query)) => filter.keyword = Some(query.to_owned()),
+            ("before", CommandDataOptionValue::String(date)) => {
+                filter.before = Some(parse_date(date).ok_or("Could not parse the before date, use YYYY-MM-DD.")?)
+            }
+            ("after", CommandDataOptionValue::String(date)) => {
+                filter.after = Some(parse_date(date).ok_or("Could not parse the after date, use YYYY-MM-DD.")?)
+            }
+            _ => return Err("Received an unknown option."),
+        }
+    }
+
+    if filter.is_empty() {
+        return Err("Specify at least one thing to purge by.");
+    }
+    Ok(filter)
+}
+
+async fn respond(ctx: &Context, interaction: &ComponentInteraction, content: &str) -> Result<()> {
+    interaction
+        .create_response(
+            ctx,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new().content(content).components(vec![]),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Suggests every channel we have quotes from, including the ones that have since been deleted.
+pub(super) async fn handle_autocomplete(ctx: Context, cmd: CommandInteraction) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+    let input = cmd.data.autocomplete().map(|option| option.value.trim().to_lowercase()).unwrap_or_default();
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let channels: Vec<(i64, String, i64)> = Quote::find()
+        .select_only()
+        .column(quote::Column::ChannelId)
+        .column_as(Expr::col(quote::Column::ChannelName).max(), "channel_name")
+        .column_as(Expr::col(quote::Column::Id).count(), "count")
         .filter(quote::Column::ServerId.eq(guild_id.get()))
-        .exec(&db)
+        .filter(
+            Func::lower(Expr::col((quote::Entity, quote::Column::ChannelName))).like(like_pattern("%", &input, "%")),
+        )
+        .group_by(quote::Column::ChannelId)
+        .order_by_desc(Expr::col(quote::Column::Id).count())
+        .limit(MAX_SUGGESTIONS)
+        .into_tuple()
+        .all(&db)
         .await?;
-    send_ephemeral_message(ctx, cmd, &format!("Quotes by {user_name} deleted!")).await
+
+    let mut response = CreateAutocompleteResponse::new();
+    for (channel_id, channel_name, count) in channels {
+        let plural = if count == 1 { "quote" } else { "quotes" };
+        response = response.add_string_choice(
+            truncate(&format!("#{channel_name} ({count} {plural})"), MAX_SUGGESTION_LENGTH),
+            channel_id.to_string(),
+        );
+    }
+
+    cmd.create_response(ctx, CreateInteractionResponse::Autocomplete(response)).await?;
+    Ok(())
 }
@@ -2,13 +2,22 @@ use anyhow::Result;
 use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 use serenity::{
     all::{Command, CommandDataOptionValue, CommandInteraction, CommandOptionType},
-    builder::{CreateCommand, CreateCommandOption},
+    builder::{CreateAutocompleteResponse, CreateCommand, CreateCommandOption, CreateInteractionResponse},
     client::Context,
 };
 
 use entity::{prelude::Quote, quote};
 
-use crate::{commands::send_ephemeral_message, quote::post_quote, util::DatabaseTypeMapKey};
+use crate::{
+    commands::send_ephemeral_message,
+    quote::{find_suggestions, post_quote, truncate},
+    util::DatabaseTypeMapKey,
+};
+
+// Discord does not allow more autocomplete choices than this
+pub(super) const MAX_SUGGESTIONS: u64 = 25;
+// Nor does it allow choice names longer than this
+pub(super) const MAX_SUGGESTION_LENGTH: usize = 100;
 
 pub(super) async fn register(ctx: &Context) -> Result<()> {
     Command::create_global_command(
@@ -16,7 +25,8 @@ pub(super) async fn register(ctx: &Context) -> Result<()> {
         CreateCommand::new("quote").description("Posts a specific quote").dm_permission(false).add_option(
             CreateCommandOption::new(CommandOptionType::Integer, "id", "A quote id (found in the bottom of a quote)")
                 .required(true)
-                .min_int_value(0),
+                .min_int_value(0)
+                .set_autocomplete(true),
         ),
     )
     .await?;
@@ -39,3 +49,21 @@ pub(super) async fn handle_command(ctx: Context, cmd: CommandInteraction) -> Res
         None => send_ephemeral_message(ctx, cmd, "Quote with that id does not exist!").await,
     }
 }
+
+/// Suggests quote ids for any command with an autocompleted quote id option, like /quote and /delete.
+pub(super) async fn handle_autocomplete(ctx: Context, cmd: CommandInteraction) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+    let input = cmd.data.autocomplete().map(|option| option.value).unwrap_or_default();
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let suggestions = find_suggestions(&db, guild_id, input, MAX_SUGGESTIONS).await?;
+
+    let mut response = CreateAutocompleteResponse::new();
+    for (id, author, text) in suggestions {
+        let text = if text.trim().is_empty() { "[image]" } else { text.as_str() };
+        response = response.add_int_choice(truncate(&format!("#{id} {author}: {text}"), MAX_SUGGESTION_LENGTH), id);
+    }
+
+    cmd.create_response(ctx, CreateInteractionResponse::Autocomplete(response)).await?;
+    Ok(())
+}
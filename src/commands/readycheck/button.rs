@@ -0,0 +1,244 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, EntityTrait, IntoActiveModel};
+use serenity::{
+    all::{ButtonStyle, ComponentInteraction, ComponentInteractionDataKind},
+    builder::{
+        CreateActionRow, CreateButton, CreateInteractionResponse, CreateInteractionResponseMessage, CreateSelectMenu,
+        CreateSelectMenuKind, CreateSelectMenuOption,
+    },
+    client::Context,
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use entity::prelude::Readycheck;
+
+use crate::{
+    commands::readycheck::{
+        config::Config,
+        lifecycle::{self, Outcome},
+        Participant,
+        ReadyState::{Maybe, NotReady, Ready, Unknown},
+        MAX_PARTICIPANTS,
+    },
+    util::DatabaseTypeMapKey,
+};
+
+// How much time the Extend button adds to a readycheck
+const EXTENSION: Duration = Duration::from_secs(5 * 60);
+// The options of the "Ready in..." select, in minutes, 0 meaning the participant doesn't know yet
+const ETA_OPTIONS: [u64; 5] = [0, 5, 10, 15, 30];
+
+/// Everything that can be done through the components of a readycheck message.
+#[derive(Clone, Copy)]
+enum Action {
+    Ready,
+    NotReady,
+    Eta,
+    Join,
+    Extend,
+    Cancel,
+}
+
+fn custom_id(readycheck_id: i64, action: Action) -> String {
+    let action = match action {
+        Action::Ready => "1",
+        Action::NotReady => "0",
+        Action::Eta => "eta",
+        Action::Join => "join",
+        Action::Extend => "extend",
+        Action::Cancel => "cancel",
+    };
+    format!("rc_{readycheck_id}_{action}")
+}
+
+fn parse_custom_id(custom_id: &str) -> Option<(i64, Action)> {
+    let (id, action) = custom_id.strip_prefix("rc_")?.split_once('_')?;
+    let action = match action {
+        "1" => Action::Ready,
+        "0" => Action::NotReady,
+        "eta" => Action::Eta,
+        "join" => Action::Join,
+        "extend" => Action::Extend,
+        "cancel" => Action::Cancel,
+        _ => return None,
+    };
+    Some((id.parse().ok()?, action))
+}
+
+/// The components attached to a running readycheck.
+pub(super) fn components(config: &Config, readycheck_id: i64) -> Vec<CreateActionRow> {
+    let eta_options = ETA_OPTIONS
+        .iter()
+        .map(|minutes| match minutes {
+            0 => CreateSelectMenuOption::new("Maybe", "0").emoji(config.emoji(Maybe)),
+            minutes => CreateSelectMenuOption::new(format!("Ready in {minutes} minutes"), minutes.to_string())
+                .emoji(config.emoji(Maybe)),
+        })
+        .collect();
+
+    vec![
+        CreateActionRow::Buttons(vec![
+            CreateButton::new(custom_id(readycheck_id, Action::Ready))
+                .emoji(config.emoji(Ready))
+                .label("Ready")
+                .style(ButtonStyle::Success),
+            CreateButton::new(custom_id(readycheck_id, Action::NotReady))
+                .emoji(config.emoji(NotReady))
+                .label("Not ready")
+                .style(ButtonStyle::Danger),
+            CreateButton::new(custom_id(readycheck_id, Action::Join)).label("Join").style(ButtonStyle::Secondary),
+        ]),
+        CreateActionRow::SelectMenu(
+            CreateSelectMenu::new(
+                custom_id(readycheck_id, Action::Eta),
+                CreateSelectMenuKind::String { options: eta_options },
+            )
+            .placeholder("Not sure yet? Let them know when you'll be ready"),
+        ),
+        CreateActionRow::Buttons(vec![
+            CreateButton::new(custom_id(readycheck_id, Action::Extend))
+                .label(format!("Extend by {} minutes", EXTENSION.as_secs() / 60))
+                .style(ButtonStyle::Primary),
+            CreateButton::new(custom_id(readycheck_id, Action::Cancel)).label("Cancel").style(ButtonStyle::Secondary),
+        ]),
+    ]
+}
+
+pub(crate) async fn press_loop(mut recv: broadcast::Receiver<(Context, ComponentInteraction)>) {
+    loop {
+        let (ctx, interaction) = match recv.recv().await {
+            Ok(interaction) => interaction,
+            Err(e) => {
+                if matches!(e, RecvError::Closed) {
+                    return;
+                }
+
+                error!("Error receiving interaction in readycheck button loop: {e}");
+                continue;
+            }
+        };
+
+        let Some((id, action)) = parse_custom_id(&interaction.data.custom_id) else { continue };
+
+        // Every press gets its own task, so a busy readycheck doesn't hold up the others or make us miss presses
+        tokio::spawn(async move {
+            if let Err(e) = pressed(ctx, interaction, id, action).await {
+                error!("Could not handle readycheck button press: {e}");
+            }
+        });
+    }
+}
+
+async fn pressed(ctx: Context, interaction: ComponentInteraction, id: i64, action: Action) -> Result<()> {
+    let _guard = lifecycle::lock(id).await;
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+
+    let model = match Readycheck::find_by_id(id).one(&db).await? {
+        Some(model) if model.ended_at.is_none() => model,
+        _ => return reply(&ctx, &interaction, "This readycheck has already ended.").await,
+    };
+    let mut participants = lifecycle::participants(&model)?;
+
+    match action {
+        Action::Extend | Action::Cancel => {
+            if interaction.user.id.get() as i64 != model.initiator_id {
+                return reply(&ctx, &interaction, "Only the one who started this readycheck can do that.").await;
+            }
+        }
+        Action::Join => {
+            if participants.iter().any(|p| p.user_id == interaction.user.id) {
+                return reply(&ctx, &interaction, "You are already part of this readycheck.").await;
+            }
+            if participants.len() >= MAX_PARTICIPANTS {
+                return reply(&ctx, &interaction, "This readycheck is full.").await;
+            }
+
+            let name = match &interaction.member {
+                Some(member) => member.display_name().to_string(),
+                None => interaction.user.name.clone(),
+            };
+            participants.push(Participant {
+                user_id: interaction.user.id,
+                name,
+                state: Unknown,
+                eta: None,
+                responded_at: None,
+                ready_at: None,
+            });
+            participants.sort_by_key(|participant| participant.name.to_owned());
+        }
+        Action::Ready | Action::NotReady | Action::Eta => {
+            let Some(participant) = participants.iter_mut().find(|p| p.user_id == interaction.user.id) else {
+                // Someone who isn't part of the readycheck pressed the button, send them an error.
+                return reply(&ctx, &interaction, "This readycheck is not for you! Press Join to take part.").await;
+            };
+
+            // Someone pressed a button, and they're part of the readycheck, mark them.
+            (participant.state, participant.eta) = match action {
+                Action::Ready => (Ready, None),
+                Action::NotReady => (NotReady, None),
+                _ => {
+                    let minutes = match &interaction.data.kind {
+                        ComponentInteractionDataKind::StringSelect { values } => {
+                            values.first().and_then(|value| value.parse::<u64>().ok()).unwrap_or_default()
+                        }
+                        _ => 0,
+                    };
+                    let eta = (minutes > 0).then(|| Utc::now() + Duration::from_secs(minutes * 60));
+                    (Maybe, eta)
+                }
+            };
+            let now = Utc::now();
+            participant.responded_at.get_or_insert(now);
+            participant.ready_at = (participant.state == Ready).then_some(now);
+        }
+    }
+
+    if let Err(e) = interaction.create_response(&ctx, CreateInteractionResponse::Acknowledge).await {
+        error!("Could not send button confirmation to user for readycheck: {e}");
+    }
+
+    match action {
+        Action::Cancel => return lifecycle::finish(&ctx, &db, model, participants, Outcome::Cancelled).await,
+        // Check if everyone is ready, if so, end the readycheck, otherwise just update the embed.
+        _ if participants.iter().all(|p| p.state == Ready) => {
+            return lifecycle::finish(&ctx, &db, model, participants, Outcome::EveryoneReady).await
+        }
+        _ => {}
+    }
+
+    let mut active = model.into_active_model();
+    active.participants = Set(serde_json::to_value(&participants)?);
+    if let Action::Extend = action {
+        let deadline = active.deadline.as_ref().to_utc() + EXTENSION;
+        active.deadline = Set(deadline.fixed_offset());
+        if let Some(reminder_at) = *active.reminder_at.as_ref() {
+            active.reminder_at = Set(Some(reminder_at + EXTENSION));
+        }
+    }
+    let model = active.update(&db).await?;
+
+    if let Action::Extend = action {
+        tokio::spawn(lifecycle::expire_at(ctx.clone(), model.id, model.deadline.to_utc()));
+        if let Some(reminder_at) = model.reminder_at {
+            tokio::spawn(lifecycle::remind_at(ctx.clone(), model.id, reminder_at.to_utc()));
+        }
+    }
+
+    lifecycle::refresh(&ctx, &db, &model, &participants).await
+}
+
+async fn reply(ctx: &Context, interaction: &ComponentInteraction, content: &str) -> Result<()> {
+    interaction
+        .create_response(
+            ctx,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().ephemeral(true).content(content),
+            ),
+        )
+        .await?;
+    Ok(())
+}
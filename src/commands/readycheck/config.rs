@@ -0,0 +1,192 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+use serenity::{
+    all::{CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType},
+    builder::CreateCommandOption,
+    client::Context,
+    model::{channel::ReactionType, id::GuildId},
+};
+
+use crate::{
+    commands::{
+        readycheck::{
+            setup::MAX_DURATION,
+            ReadyState::{self, Maybe, NotReady, Ready, Unknown},
+        },
+        send_ephemeral_message,
+    },
+    util::{format_duration, kvstore, parse_duration, DatabaseTypeMapKey},
+};
+
+const DEFAULT_TITLE: &str = "Readycheck!";
+const DEFAULT_DESCRIPTION: &str = "Ready the feck up!";
+// How long a finished readycheck stays visible before we clean it up, unless configured otherwise
+const DEFAULT_CLEANUP_DELAY: Duration = Duration::from_secs(10 * 60);
+// The title also holds the ready count, and Discord doesn't allow embed titles over 256 characters
+const MAX_TITLE_LENGTH: u16 = 200;
+const MAX_DESCRIPTION_LENGTH: u16 = 1000;
+
+/// How a guild wants its readychecks to look, anything left empty uses our defaults.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub(super) struct Config {
+    title: Option<String>,
+    description: Option<String>,
+    ready_emoji: Option<String>,
+    not_ready_emoji: Option<String>,
+    maybe_emoji: Option<String>,
+    pending_emoji: Option<String>,
+    // In seconds
+    cleanup_delay: Option<u64>,
+}
+
+fn key(guild_id: GuildId) -> String {
+    format!("readycheck_config_{guild_id}")
+}
+
+/// Removes the configuration of a guild, for when the bot is removed from it.
+pub(crate) async fn forget(db: &DatabaseConnection, guild_id: GuildId) -> Result<()> {
+    kvstore::delete(db, &key(guild_id)).await
+}
+
+impl Config {
+    pub(super) async fn load(db: &DatabaseConnection, guild_id: GuildId) -> Result<Self> {
+        Ok(kvstore::get(db, &key(guild_id)).await?.unwrap_or_default())
+    }
+
+    pub(super) fn title(&self) -> &str {
+        self.title.as_deref().unwrap_or(DEFAULT_TITLE)
+    }
+
+    pub(super) fn description(&self) -> &str {
+        self.description.as_deref().unwrap_or(DEFAULT_DESCRIPTION)
+    }
+
+    /// The emoji for a state, falling back to its Unicode default if the configured one can't be used.
+    pub(super) fn emoji(&self, state: ReadyState) -> ReactionType {
+        let configured = match state {
+            Unknown => &self.pending_emoji,
+            Ready => &self.ready_emoji,
+            Maybe => &self.maybe_emoji,
+            NotReady => &self.not_ready_emoji,
+        };
+        configured
+            .as_deref()
+            .and_then(|emoji| ReactionType::try_from(emoji).ok())
+            .unwrap_or_else(|| state.default_emoji())
+    }
+
+    pub(super) fn cleanup_delay(&self) -> Duration {
+        self.cleanup_delay.map(Duration::from_secs).unwrap_or(DEFAULT_CLEANUP_DELAY)
+    }
+}
+
+pub(super) fn config_options() -> Vec<CreateCommandOption> {
+    vec![
+        CreateCommandOption::new(CommandOptionType::String, "title", "The title of the readycheck")
+            .max_length(MAX_TITLE_LENGTH),
+        CreateCommandOption::new(CommandOptionType::String, "description", "The text shown below the title")
+            .max_length(MAX_DESCRIPTION_LENGTH),
+        CreateCommandOption::new(CommandOptionType::String, "ready_emoji", "The emoji for people that are ready"),
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "not_ready_emoji",
+            "The emoji for people that aren't ready",
+        ),
+        CreateCommandOption::new(CommandOptionType::String, "maybe_emoji", "The emoji for people that might be ready"),
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "pending_emoji",
+            "The emoji for people that haven't responded",
+        ),
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "cleanup_delay",
+            "How long a finished readycheck stays visible, like 10m or 1h",
+        ),
+        CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "reset",
+            "Go back to the defaults before applying the rest",
+        ),
+    ]
+}
+
+pub(super) async fn handle_config(ctx: Context, cmd: CommandInteraction, options: &[CommandDataOption]) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+
+    let permissions = match cmd.member.as_ref().and_then(|m| m.permissions) {
+        Some(p) => p,
+        None => return Err(anyhow!("Could not fetch member permissions")),
+    };
+    if !permissions.manage_guild() {
+        return send_ephemeral_message(ctx, cmd, "You need the Manage Server permission to change this.").await;
+    }
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let mut config = if options.iter().any(|o| o.name == "reset" && o.value == CommandDataOptionValue::Boolean(true)) {
+        Config::default()
+    } else {
+        Config::load(&db, guild_id).await?
+    };
+
+    for option in options {
+        let CommandDataOptionValue::String(value) = &option.value else { continue };
+        let value = value.trim().to_string();
+        match option.name.as_str() {
+            "title" => config.title = Some(value),
+            "description" => config.description = Some(value),
+            "cleanup_delay" => match parse_duration(&value).filter(|delay| *delay <= MAX_DURATION) {
+                Some(delay) => config.cleanup_delay = Some(delay.as_secs()),
+                None => {
+                    let error = format!(
+                        "Could not use that cleanup delay, try something like 10m or 1h (up to {}).",
+                        format_duration(MAX_DURATION)
+                    );
+                    return send_ephemeral_message(ctx, cmd, &error).await;
+                }
+            },
+            name => {
+                let field = match name {
+                    "ready_emoji" => &mut config.ready_emoji,
+                    "not_ready_emoji" => &mut config.not_ready_emoji,
+                    "maybe_emoji" => &mut config.maybe_emoji,
+                    "pending_emoji" => &mut config.pending_emoji,
+                    _ => continue,
+                };
+                if !usable_emoji(&ctx, guild_id, &value).await {
+                    let error = format!("{value} is not an emoji that can be used in this server.");
+                    return send_ephemeral_message(ctx, cmd, &error).await;
+                }
+                *field = Some(value);
+            }
+        }
+    }
+
+    kvstore::set(&db, &key(guild_id), &config).await?;
+
+    let summary = format!(
+        "Readychecks in this server now look like this:\n**Title:** {}\n**Description:** {}\n**Emojis:** {} ready, {} not ready, {} maybe, {} pending\n**Cleaned up after:** {}",
+        config.title(),
+        config.description(),
+        config.emoji(Ready),
+        config.emoji(NotReady),
+        config.emoji(Maybe),
+        config.emoji(Unknown),
+        format_duration(config.cleanup_delay()),
+    );
+    send_ephemeral_message(ctx, cmd, &summary).await
+}
+
+/// Custom emojis have to come from this server, as emojis from other servers render broken for most people.
+async fn usable_emoji(ctx: &Context, guild_id: GuildId, emoji: &str) -> bool {
+    match ReactionType::try_from(emoji) {
+        Ok(ReactionType::Custom { id, .. }) => guild_id.emoji(ctx, id).await.is_ok(),
+        // Anything else is taken as Unicode, which at least shouldn't be plain text
+        Ok(ReactionType::Unicode(unicode)) => !unicode.is_ascii() && unicode.chars().count() <= 8,
+        _ => false,
+    }
+}
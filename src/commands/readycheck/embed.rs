@@ -0,0 +1,121 @@
+use chrono::{DateTime, Utc};
+use serenity::{builder::CreateEmbed, model::Colour, prelude::Mentionable};
+
+use crate::commands::readycheck::{
+    config::Config,
+    Participant,
+    ReadyState::{Maybe, NotReady, Ready, Unknown},
+};
+
+// Up to this many participants, everyone gets their own field. Discord doesn't allow more fields than that.
+const MAX_FIELDS: usize = 25;
+// Discord's limits for the embeds of a single message
+const MAX_EMBEDS: usize = 10;
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+const MAX_TOTAL_LENGTH: usize = 6000;
+// Room we keep free to mention that not everyone fit, should it come to that
+const TRUNCATION_RESERVE: usize = 32;
+
+pub(super) fn create_embeds(
+    config: &Config,
+    participants: &[Participant],
+    deadline: DateTime<Utc>,
+    expired: bool,
+) -> Vec<CreateEmbed> {
+    let colour = if expired { Colour::DARK_GREY } else { Colour::FABLED_PINK };
+    let ready = participants.iter().filter(|p| p.state == Ready).count();
+    let title = format!("{} ({ready}/{} ready)", config.title(), participants.len());
+    let mut description = config.description().to_string();
+    if !expired {
+        description += &format!("\nEnds <t:{}:R>", deadline.timestamp());
+    }
+
+    if participants.len() <= MAX_FIELDS {
+        let mut e = CreateEmbed::default().colour(colour);
+        for participant in participants.iter() {
+            e = e.field(format!("{} {}", config.emoji(participant.state), participant.name), eta(participant), true);
+        }
+        return vec![e.title(title).description(description)];
+    }
+
+    // Too many people for fields, so we list them per state in the description, spread over multiple embeds
+    let mut writer = DescriptionWriter {
+        descriptions: vec![description.clone()],
+        remaining: MAX_TOTAL_LENGTH - TRUNCATION_RESERVE - title.chars().count() - description.chars().count(),
+    };
+    let mut left_out = 0;
+    for (state, label) in [(Ready, "Ready"), (Maybe, "Maybe"), (NotReady, "Not ready"), (Unknown, "Pending")] {
+        let members: Vec<&Participant> = participants.iter().filter(|p| p.state == state).collect();
+        if members.is_empty() {
+            continue;
+        }
+
+        if !writer.push(&format!("\n\n{} **{label}** ({})\n", config.emoji(state), members.len())) {
+            left_out += members.len();
+            continue;
+        }
+        for member in members {
+            let eta = match member.eta {
+                Some(_) => format!(" ({})", eta(member)),
+                None => String::new(),
+            };
+            if !writer.push(&format!("{}{eta} ", member.user_id.mention())) {
+                left_out += 1;
+            }
+        }
+    }
+    if left_out > 0 {
+        writer.remaining += TRUNCATION_RESERVE;
+        writer.push(&format!("\n…and {left_out} more"));
+    }
+
+    writer
+        .descriptions
+        .into_iter()
+        .enumerate()
+        .map(|(index, description)| {
+            let e = CreateEmbed::default().colour(colour).description(description);
+            if index == 0 {
+                e.title(title.as_str())
+            } else {
+                e
+            }
+        })
+        .collect()
+}
+
+/// When a participant expects to be ready, if they told us.
+fn eta(participant: &Participant) -> String {
+    match participant.eta {
+        Some(eta) => format!("ready <t:{}:R>", eta.timestamp()),
+        None => String::new(),
+    }
+}
+
+struct DescriptionWriter {
+    descriptions: Vec<String>,
+    // The amount of characters we can still add to the message as a whole
+    remaining: usize,
+}
+
+impl DescriptionWriter {
+    /// Appends the text to the last embed, or starts a new one if it doesn't fit. Returns false if out of room.
+    fn push(&mut self, text: &str) -> bool {
+        let length = text.chars().count();
+        if length > self.remaining {
+            return false;
+        }
+
+        let current = self.descriptions.last().map_or(0, |d| d.chars().count());
+        if current + length > MAX_DESCRIPTION_LENGTH {
+            if self.descriptions.len() >= MAX_EMBEDS {
+                return false;
+            }
+            self.descriptions.push(String::new());
+        }
+
+        self.descriptions.last_mut().unwrap().push_str(text);
+        self.remaining -= length;
+        true
+    }
+}
@@ -0,0 +1,277 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    },
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, Condition, DatabaseConnection, EntityTrait, IntoActiveModel,
+    QueryFilter,
+};
+use serenity::{
+    builder::{CreateMessage, EditMessage},
+    client::Context,
+    model::id::{ChannelId, GuildId, MessageId, UserId},
+    prelude::Mentionable,
+};
+use tokio::{
+    sync::{Mutex, OwnedMutexGuard},
+    time::sleep,
+};
+
+use entity::{prelude::Readycheck, readycheck};
+
+use crate::{
+    commands::readycheck::{
+        config::Config,
+        embed::create_embeds,
+        schedule, Participant,
+        ReadyState::{NotReady, Unknown},
+    },
+    util::DatabaseTypeMapKey,
+};
+
+// Readychecks are read, modified and written back, so all changes to one go through its own lock
+static LOCKS: OnceLock<std::sync::Mutex<HashMap<i64, Arc<Mutex<()>>>>> = OnceLock::new();
+// The ready event fires on every reconnect, but we only want to resume once
+static RESUMED: AtomicBool = AtomicBool::new(false);
+
+pub(super) async fn lock(id: i64) -> OwnedMutexGuard<()> {
+    let lock = {
+        let mut locks = LOCKS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+        // Locks nobody holds or waits for anymore can go, they're made again when needed
+        locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+        locks.entry(id).or_default().clone()
+    };
+    lock.lock_owned().await
+}
+
+pub(super) fn participants(model: &readycheck::Model) -> Result<Vec<Participant>> {
+    Ok(serde_json::from_value(model.participants.clone())?)
+}
+
+/// Updates the message of a running readycheck to show the current state of its participants.
+pub(super) async fn refresh(
+    ctx: &Context,
+    db: &DatabaseConnection,
+    model: &readycheck::Model,
+    participants: &[Participant],
+) -> Result<()> {
+    if let Some(message_id) = model.message_id {
+        let config = Config::load(db, GuildId::new(model.server_id as u64)).await?;
+        ChannelId::new(model.channel_id as u64)
+            .edit_message(
+                ctx,
+                MessageId::new(message_id as u64),
+                EditMessage::new().embeds(create_embeds(&config, participants, model.deadline.to_utc(), false)),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+/// Why a readycheck ended.
+pub(super) enum Outcome {
+    EveryoneReady,
+    Expired,
+    Cancelled,
+}
+
+impl Outcome {
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::EveryoneReady => "ready",
+            Outcome::Expired => "expired",
+            Outcome::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Ends a readycheck: everyone that hasn't responded is marked as not ready, and the buttons are removed.
+/// The caller is expected to hold the readycheck lock.
+pub(super) async fn finish(
+    ctx: &Context,
+    db: &DatabaseConnection,
+    model: readycheck::Model,
+    mut participants: Vec<Participant>,
+    outcome: Outcome,
+) -> Result<()> {
+    // Mark everyone that has not responded as not ready, unless the readycheck was called off.
+    if !matches!(outcome, Outcome::Cancelled) {
+        for participant in participants.iter_mut() {
+            if participant.state == Unknown {
+                participant.state = NotReady;
+            }
+        }
+    }
+
+    let ended_at = Utc::now();
+    let mut active = model.into_active_model();
+    active.participants = Set(serde_json::to_value(&participants)?);
+    active.ended_at = Set(Some(ended_at.fixed_offset()));
+    active.outcome = Set(Some(outcome.as_str().to_string()));
+    let model = active.update(db).await?;
+
+    let config = Config::load(db, GuildId::new(model.server_id as u64)).await?;
+    let channel = ChannelId::new(model.channel_id as u64);
+    if let Some(message_id) = model.message_id {
+        let embeds = create_embeds(&config, &participants, model.deadline.to_utc(), true);
+        let mut edit = EditMessage::new().components(vec![]).embeds(embeds);
+        if let Outcome::Cancelled = outcome {
+            edit = edit.content("This readycheck was cancelled.");
+        }
+        channel.edit_message(ctx, MessageId::new(message_id as u64), edit).await?;
+    }
+    if let Outcome::EveryoneReady = outcome {
+        let initiator = UserId::new(model.initiator_id as u64);
+        channel
+            .send_message(ctx, CreateMessage::new().content(format!("{}, everyone is ready!", initiator.mention())))
+            .await?;
+    }
+
+    tokio::spawn(cleanup_at(ctx.clone(), model.id, ended_at + config.cleanup_delay()));
+    Ok(())
+}
+
+/// Waits for the deadline of a readycheck, and ends it if nobody else did so already.
+pub(super) async fn expire_at(ctx: Context, id: i64, deadline: DateTime<Utc>) {
+    sleep_until(deadline).await;
+    if let Err(e) = expire(&ctx, id).await {
+        error!("Could not expire readycheck {id}: {e}");
+    }
+}
+
+async fn expire(ctx: &Context, id: i64) -> Result<()> {
+    let _guard = lock(id).await;
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let Some(model) = Readycheck::find_by_id(id).one(&db).await? else { return Ok(()) };
+    // If the readycheck was extended in the meantime, another task is waiting for the new deadline
+    if model.ended_at.is_some() || model.deadline.to_utc() > Utc::now() {
+        return Ok(());
+    }
+
+    let participants = participants(&model)?;
+    finish(ctx, &db, model, participants, Outcome::Expired).await
+}
+
+/// Waits until shortly before the deadline of a readycheck, and reminds everyone that hasn't responded yet.
+pub(super) async fn remind_at(ctx: Context, id: i64, at: DateTime<Utc>) {
+    sleep_until(at).await;
+    if let Err(e) = remind(&ctx, id).await {
+        error!("Could not send reminders for readycheck {id}: {e}");
+    }
+}
+
+async fn remind(ctx: &Context, id: i64) -> Result<()> {
+    let (model, participants) = {
+        let _guard = lock(id).await;
+        let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+        let Some(model) = Readycheck::find_by_id(id).one(&db).await? else { return Ok(()) };
+        if model.ended_at.is_some() || model.reminder_at.is_none_or(|at| at.to_utc() > Utc::now()) {
+            return Ok(());
+        }
+
+        // Mark the reminder as sent before sending it, so a restart never reminds people twice
+        let participants = participants(&model)?;
+        let mut active = model.into_active_model();
+        active.reminder_at = Set(None);
+        (active.update(&db).await?, participants)
+    };
+
+    // The DMs are sent without holding the lock, so button presses don't have to wait on them
+    let channel = ChannelId::new(model.channel_id as u64);
+    let link = model
+        .message_id
+        .map(|message_id| MessageId::new(message_id as u64).link(channel, Some(GuildId::new(model.server_id as u64))));
+    let content = format!(
+        "You haven't responded to the readycheck in {} yet, it ends <t:{}:R>! {}",
+        channel.mention(),
+        model.deadline.timestamp(),
+        link.unwrap_or_default()
+    );
+    for participant in participants.iter().filter(|p| p.state == Unknown) {
+        if let Err(e) = participant.user_id.direct_message(ctx, CreateMessage::new().content(content.as_str())).await {
+            info!("Could not send readycheck reminder to {}: {e}", participant.user_id);
+        }
+    }
+    Ok(())
+}
+
+async fn cleanup_at(ctx: Context, id: i64, at: DateTime<Utc>) {
+    sleep_until(at).await;
+    if let Err(e) = cleanup(&ctx, id).await {
+        error!("Could not clean up readycheck {id}: {e}");
+    }
+}
+
+async fn cleanup(ctx: &Context, id: i64) -> Result<()> {
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let Some(model) = Readycheck::find_by_id(id).one(&db).await? else { return Ok(()) };
+    let Some(message_id) = model.message_id else { return Ok(()) };
+
+    let channel = ChannelId::new(model.channel_id as u64);
+    if let Err(e) = channel.delete_message(ctx, MessageId::new(message_id as u64)).await {
+        error!("Could not delete finished readycheck: {e}");
+    }
+
+    // The readycheck itself is kept for the stats, we just forget about its message
+    let mut active = model.into_active_model();
+    active.message_id = Set(None);
+    active.update(&db).await?;
+    Ok(())
+}
+
+/// Picks up all readychecks that were running (or waiting to be cleaned up) when the bot was last stopped,
+/// and starts the scheduler for scheduled readychecks.
+pub(crate) async fn resume(ctx: Context) {
+    if RESUMED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    // Finished readychecks that were cleaned up already no longer have a message
+    let readychecks = match Readycheck::find()
+        .filter(
+            Condition::any()
+                .add(readycheck::Column::EndedAt.is_null())
+                .add(readycheck::Column::MessageId.is_not_null()),
+        )
+        .all(&db)
+        .await
+    {
+        Ok(readychecks) => readychecks,
+        Err(e) => {
+            error!("Could not load readychecks to resume: {e}");
+            return;
+        }
+    };
+
+    for model in readychecks {
+        if let (None, Some(reminder_at)) = (model.ended_at, model.reminder_at) {
+            tokio::spawn(remind_at(ctx.clone(), model.id, reminder_at.to_utc()));
+        }
+        match model.ended_at {
+            Some(ended_at) => {
+                let cleanup_delay = match Config::load(&db, GuildId::new(model.server_id as u64)).await {
+                    Ok(config) => config.cleanup_delay(),
+                    Err(e) => {
+                        error!("Could not load readycheck config for {}: {e}", model.server_id);
+                        Config::default().cleanup_delay()
+                    }
+                };
+                tokio::spawn(cleanup_at(ctx.clone(), model.id, ended_at.to_utc() + cleanup_delay))
+            }
+            None => tokio::spawn(expire_at(ctx.clone(), model.id, model.deadline.to_utc())),
+        };
+    }
+
+    tokio::spawn(schedule::run(ctx));
+}
+
+async fn sleep_until(at: DateTime<Utc>) {
+    sleep((at - Utc::now()).to_std().unwrap_or_default()).await;
+}
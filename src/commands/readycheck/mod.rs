@@ -0,0 +1,405 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, IntoActiveModel};
+use serde::{Deserialize, Serialize};
+use serenity::{
+    all::{Command, CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType, RoleId, UserId},
+    builder::{CreateCommand, CreateCommandOption, CreateMessage, EditInteractionResponse},
+    client::Context,
+    futures::StreamExt,
+    model::{channel::ReactionType, id::GuildId, prelude::ChannelId, Permissions},
+    prelude::Mentionable,
+};
+
+use entity::readycheck;
+
+use crate::{
+    commands::{
+        edit_interaction,
+        readycheck::{
+            config::Config,
+            embed::create_embeds,
+            setup::{setup, SetupResult},
+            ReadyState::{Maybe, NotReady, Ready, Unknown},
+        },
+        send_ephemeral_message,
+    },
+    handler::Handler,
+    util::DatabaseTypeMapKey,
+};
+
+pub(super) use schedule::handle_autocomplete;
+
+pub(crate) mod button;
+pub(crate) mod config;
+mod embed;
+pub(crate) mod lifecycle;
+mod schedule;
+mod setup;
+pub(crate) mod stats;
+pub(crate) mod voice;
+
+// Past this many participants we can no longer fit everyone's mention into a single message
+const MAX_PARTICIPANTS: usize = 250;
+// Discord doesn't allow longer messages than this
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+pub(super) async fn register(ctx: &Context) -> Result<()> {
+    Command::create_global_command(
+        ctx,
+        CreateCommand::new("readycheck")
+            .description("Readychecks for a group of people")
+            .default_member_permissions(Permissions::MENTION_EVERYONE)
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "start", "Starts a readycheck right now")
+                    .add_sub_option(reminder_option())
+                    .add_sub_option(leave_not_ready_option()),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "schedule",
+                    "Schedules a readycheck for later, optionally repeating it",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "time", "The time to start at (HH:MM)")
+                        .required(true),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "repeat", "How often to repeat it")
+                        .add_string_choice("Once", "once")
+                        .add_string_choice("Daily", "daily")
+                        .add_string_choice("Weekly", "weekly"),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "day", "The first weekday to start on")
+                        .add_string_choice("Monday", "monday")
+                        .add_string_choice("Tuesday", "tuesday")
+                        .add_string_choice("Wednesday", "wednesday")
+                        .add_string_choice("Thursday", "thursday")
+                        .add_string_choice("Friday", "friday")
+                        .add_string_choice("Saturday", "saturday")
+                        .add_string_choice("Sunday", "sunday"),
+                )
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "date",
+                    "The first date to start on (YYYY-MM-DD)",
+                ))
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "timezone",
+                    "The timezone of the time, like Europe/Amsterdam (defaults to UTC)",
+                ))
+                .add_sub_option(reminder_option())
+                .add_sub_option(leave_not_ready_option()),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "config",
+                    "Changes how readychecks look in this server",
+                )
+                .set_sub_options(config::config_options()),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "schedules",
+                "Lists the scheduled readychecks",
+            ))
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "stats",
+                    "Shows how quickly everyone responds to readychecks",
+                )
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::User,
+                    "user",
+                    "Only show the stats of this member",
+                )),
+            )
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "unschedule", "Removes a scheduled readycheck")
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::Integer, "id", "The scheduled readycheck")
+                            .required(true)
+                            .set_autocomplete(true),
+                    ),
+            ),
+    )
+    .await?;
+    Ok(())
+}
+
+fn reminder_option() -> CreateCommandOption {
+    CreateCommandOption::new(
+        CommandOptionType::Integer,
+        "reminder",
+        "DM everyone that hasn't responded this many minutes before the deadline",
+    )
+    .min_int_value(1)
+    .max_int_value(60)
+}
+
+fn leave_not_ready_option() -> CreateCommandOption {
+    CreateCommandOption::new(
+        CommandOptionType::Boolean,
+        "leave_not_ready",
+        "Mark people that leave the selected voice channel as not ready",
+    )
+}
+
+pub(super) async fn handle_command(handler: &Handler, ctx: Context, cmd: CommandInteraction) -> Result<()> {
+    if cmd.guild_id.is_none() {
+        return send_ephemeral_message(ctx, cmd, "This command can only be used in servers.").await;
+    }
+
+    let Some((subcommand, options)) = cmd.data.options.first().and_then(|option| match &option.value {
+        CommandDataOptionValue::SubCommand(options) => Some((option.name.clone(), options.clone())),
+        _ => None,
+    }) else {
+        return send_ephemeral_message(ctx, cmd, "No subcommand passed").await;
+    };
+
+    match subcommand.as_str() {
+        "start" => handle_start(handler, ctx, cmd, &options).await,
+        "schedule" => schedule::handle_schedule(handler, ctx, cmd, &options).await,
+        "schedules" => schedule::handle_list(ctx, cmd).await,
+        "unschedule" => schedule::handle_unschedule(ctx, cmd, &options).await,
+        "stats" => stats::handle_stats(ctx, cmd, &options).await,
+        "config" => config::handle_config(ctx, cmd, &options).await,
+        _ => send_ephemeral_message(ctx, cmd, "Unknown subcommand").await,
+    }
+}
+
+async fn handle_start(
+    handler: &Handler,
+    ctx: Context,
+    cmd: CommandInteraction,
+    options: &[CommandDataOption],
+) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+
+    // Start the setup wizard, allowing the selection of roles, users, a voice channel and the duration
+    let (duration, roles, users, voice_channel) = match setup(handler, &ctx, &cmd, "Start readycheck!").await? {
+        SetupResult::Invalid(e) => {
+            cmd.edit_response(&ctx, EditInteractionResponse::new().content(e).components(vec![])).await?;
+            return Ok(());
+        }
+        SetupResult::Valid { duration, roles, users, voice_channel } => (duration, roles, users, voice_channel),
+    };
+
+    let request = Request {
+        guild_id,
+        channel_id: cmd.channel_id,
+        initiator: cmd.user.id,
+        duration,
+        reminder: parse_reminder(options),
+        roles,
+        users,
+        voice_channel,
+        leave_not_ready: parse_leave_not_ready(options),
+    };
+    match start(&ctx, request).await? {
+        Some(e) => edit_interaction(ctx, cmd, e).await,
+        None => Ok(cmd.delete_response(&ctx).await?),
+    }
+}
+
+fn parse_reminder(options: &[CommandDataOption]) -> Option<Duration> {
+    options.iter().find(|option| option.name == "reminder").and_then(|option| match option.value {
+        CommandDataOptionValue::Integer(minutes) => Some(Duration::from_secs(minutes.max(0) as u64 * 60)),
+        _ => None,
+    })
+}
+
+fn parse_leave_not_ready(options: &[CommandDataOption]) -> bool {
+    options
+        .iter()
+        .any(|option| option.name == "leave_not_ready" && option.value == CommandDataOptionValue::Boolean(true))
+}
+
+/// Everything needed to start a readycheck, whether it was requested right now or scheduled earlier.
+struct Request {
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    initiator: UserId,
+    duration: Duration,
+    reminder: Option<Duration>,
+    roles: Vec<RoleId>,
+    users: Vec<UserId>,
+    // Everyone in this voice channel is included as well
+    voice_channel: Option<ChannelId>,
+    leave_not_ready: bool,
+}
+
+/// Posts a new readycheck and stores it, so it can be picked up by the button loop and survives restarts.
+/// Returns a message for the user if the readycheck could not be started.
+async fn start(ctx: &Context, request: Request) -> Result<Option<&'static str>> {
+    let Request { guild_id, channel_id, initiator, duration, reminder, roles, users, voice_channel, leave_not_ready } =
+        request;
+
+    // Whoever is in the voice channel right now, as far as the cache knows
+    let voice_users: Vec<UserId> = match voice_channel {
+        Some(voice_channel) => ctx
+            .cache
+            .guild(guild_id)
+            .map(|guild| {
+                guild
+                    .voice_states
+                    .values()
+                    .filter(|state| state.channel_id == Some(voice_channel))
+                    .map(|state| state.user_id)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    let mentions = roles
+        .iter()// Create an iterator
+        .map(|r| r.mention()) // Map all roles to Mentions
+        .chain(users.iter().chain(voice_users.iter()).map(|u| u.mention())) // Chain all users mapped to mentions at the end
+        .map(|m| m.to_string()) // Convert to strings
+        .collect::<Vec<_>>();
+
+    // Go through all members, and filter them to see if they have the role.
+    // Then, while doing so, give them the "Unknown" status so they can fill it in themselves.
+    let mut participants = Vec::new();
+    let mut members = guild_id.members_iter(ctx).boxed();
+    while let Some(member) = members.next().await {
+        let m = member?;
+        if !users.contains(&m.user.id)
+            && !voice_users.contains(&m.user.id)
+            && !roles.iter().any(|role| m.roles.contains(role))
+        {
+            continue;
+        }
+
+        participants.push(Participant {
+            user_id: m.user.id,
+            name: m.nick.clone().unwrap_or_else(|| m.user.name.clone()),
+            state: Unknown,
+            eta: None,
+            responded_at: None,
+            ready_at: None,
+        });
+    }
+
+    if participants.is_empty() {
+        return Ok(Some("Can't send a readycheck to zero people."));
+    }
+    if participants.len() > MAX_PARTICIPANTS {
+        return Ok(Some("The readycheck only works for up to 250 people."));
+    }
+
+    participants.sort_by_key(|participant| participant.name.to_owned());
+
+    // Store the readycheck first, as its id is what routes the button presses to it
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let now = Utc::now();
+    let model = readycheck::ActiveModel {
+        id: Default::default(),
+        server_id: Set(guild_id.get() as i64),
+        channel_id: Set(channel_id.get() as i64),
+        message_id: Set(None),
+        initiator_id: Set(initiator.get() as i64),
+        deadline: Set((now + duration).fixed_offset()),
+        participants: Set(serde_json::to_value(&participants)?),
+        ended_at: Set(None),
+        // A reminder only makes sense if it goes out after the readycheck started
+        reminder_at: Set(reminder.filter(|r| *r < duration).map(|r| (now + duration - r).fixed_offset())),
+        voice_channel_id: Set(voice_channel.map(|channel| channel.get() as i64)),
+        leave_not_ready: Set(leave_not_ready && voice_channel.is_some()),
+        started_at: Set(Some(now.fixed_offset())),
+        outcome: Set(None),
+    }
+    .insert(&db)
+    .await?;
+
+    // Send the initial message with the buttons attached
+    let config = Config::load(&db, guild_id).await?;
+    let readycheck_msg = channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new()
+                .embeds(create_embeds(&config, &participants, model.deadline.to_utc(), false))
+                .components(button::components(&config, model.id)),
+        )
+        .await?;
+
+    let mut model = model.into_active_model();
+    model.message_id = Set(Some(readycheck_msg.id.get() as i64));
+    let model = model.update(&db).await?;
+
+    tokio::spawn(shadow_ping(ctx.clone(), mentions, channel_id));
+    tokio::spawn(lifecycle::expire_at(ctx.clone(), model.id, model.deadline.to_utc()));
+    if let Some(reminder_at) = model.reminder_at {
+        tokio::spawn(lifecycle::remind_at(ctx.clone(), model.id, reminder_at.to_utc()));
+    }
+
+    Ok(None)
+}
+
+async fn shadow_ping(ctx: Context, mentions: Vec<String>, channel: ChannelId) -> Result<()> {
+    // Split the mentions over as many messages as needed to fit within Discord's message length
+    let mut messages = vec![String::new()];
+    for mention in mentions {
+        let current = messages.last_mut().unwrap();
+        if current.len() + mention.len() + 1 > MAX_MESSAGE_LENGTH {
+            messages.push(mention);
+        } else {
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(&mention);
+        }
+    }
+
+    for content in messages.into_iter().filter(|content| !content.is_empty()) {
+        let msg = channel.send_message(&ctx, CreateMessage::new().content(content)).await?;
+        msg.delete(&ctx).await?;
+    }
+    Ok(())
+}
+
+/// Someone that is part of a readycheck, as stored in the database.
+#[derive(Serialize, Deserialize)]
+struct Participant {
+    user_id: UserId,
+    name: String,
+    state: ReadyState,
+    // When someone that answered maybe expects to be ready
+    #[serde(default)]
+    eta: Option<DateTime<Utc>>,
+    // When they first responded, and when they became ready, for the stats
+    #[serde(default)]
+    responded_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    ready_at: Option<DateTime<Utc>>,
+}
+
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize)]
+enum ReadyState {
+    Unknown,
+    Ready,
+    Maybe,
+    NotReady,
+}
+
+impl ReadyState {
+    /// The emoji used when a guild hasn't configured one, these render the same everywhere.
+    fn default_emoji(self) -> ReactionType {
+        let emoji = match self {
+            Unknown => "❔",
+            Ready => "✅",
+            Maybe => "⏳",
+            NotReady => "❌",
+        };
+        ReactionType::Unicode(emoji.to_string())
+    }
+}
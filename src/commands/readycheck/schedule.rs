@@ -0,0 +1,434 @@
+use std::{iter::successors, time::Duration};
+
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Days, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, QueryOrder, QuerySelect,
+};
+use serenity::{
+    all::{CommandDataOption, CommandDataOptionValue, CommandInteraction, RoleId, UserId},
+    builder::{CreateAutocompleteResponse, CreateInteractionResponse, EditInteractionResponse},
+    client::Context,
+    model::id::{ChannelId, GuildId},
+    prelude::Mentionable,
+};
+use tokio::time::sleep;
+
+use entity::{prelude::ReadycheckSchedule, readycheck_schedule};
+
+use crate::{
+    commands::{
+        quote::MAX_SUGGESTIONS,
+        readycheck::{
+            parse_leave_not_ready, parse_reminder,
+            setup::{setup, SetupResult},
+            start, Request, MAX_MESSAGE_LENGTH,
+        },
+        send_ephemeral_message,
+    },
+    handler::Handler,
+    quote::truncate,
+    util::DatabaseTypeMapKey,
+};
+
+// How often we check for scheduled readychecks that are due
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy)]
+enum Repeat {
+    Once,
+    Daily,
+    Weekly,
+}
+
+impl Repeat {
+    fn parse(repeat: &str) -> Option<Self> {
+        match repeat {
+            "once" => Some(Self::Once),
+            "daily" => Some(Self::Daily),
+            "weekly" => Some(Self::Weekly),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Once => "once",
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+        }
+    }
+
+    fn interval(self) -> Option<Days> {
+        match self {
+            Self::Once => None,
+            Self::Daily => Some(Days::new(1)),
+            Self::Weekly => Some(Days::new(7)),
+        }
+    }
+}
+
+struct ScheduleOptions {
+    time: NaiveTime,
+    day: Option<Weekday>,
+    date: Option<NaiveDate>,
+    timezone: Tz,
+    repeat: Repeat,
+    reminder: Option<Duration>,
+    leave_not_ready: bool,
+}
+
+fn parse_options(options: &[CommandDataOption]) -> Result<ScheduleOptions, &'static str> {
+    let mut time = None;
+    let mut day = None;
+    let mut date = None;
+    let mut timezone = Tz::UTC;
+    let mut repeat = Repeat::Once;
+    for option in options {
+        match (option.name.as_str(), &option.value) {
+            ("time", CommandDataOptionValue::String(value)) => {
+                time = Some(
+                    NaiveTime::parse_from_str(value.trim(), "%H:%M")
+                        .map_err(|_| "Could not parse the time, use HH:MM.")?,
+                )
+            }
+            ("day", CommandDataOptionValue::String(value)) => {
+                day = Some(value.parse().map_err(|_| "Could not parse the day.")?)
+            }
+            ("date", CommandDataOptionValue::String(value)) => {
+                date = Some(
+                    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+                        .map_err(|_| "Could not parse the date, use YYYY-MM-DD.")?,
+                )
+            }
+            ("timezone", CommandDataOptionValue::String(value)) => {
+                timezone = value.trim().parse().map_err(|_| "Unknown timezone, use something like Europe/Amsterdam.")?
+            }
+            ("repeat", CommandDataOptionValue::String(value)) => {
+                repeat = Repeat::parse(value).ok_or("Could not parse how often to repeat.")?
+            }
+            ("reminder", CommandDataOptionValue::Integer(_)) => {}
+            ("leave_not_ready", CommandDataOptionValue::Boolean(_)) => {}
+            _ => return Err("Received an unknown option."),
+        }
+    }
+
+    if day.is_some() && date.is_some() {
+        return Err("Pick either a day or a date, not both.");
+    }
+
+    Ok(ScheduleOptions {
+        time: time.ok_or("No time passed.")?,
+        day,
+        date,
+        timezone,
+        repeat,
+        reminder: parse_reminder(options),
+        leave_not_ready: parse_leave_not_ready(options),
+    })
+}
+
+pub(super) async fn handle_schedule(
+    handler: &Handler,
+    ctx: Context,
+    cmd: CommandInteraction,
+    options: &[CommandDataOption],
+) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+    let options = match parse_options(options) {
+        Ok(options) => options,
+        Err(e) => return send_ephemeral_message(ctx, cmd, e).await,
+    };
+
+    let now = Utc::now();
+    let first_run = match options.date {
+        Some(date) => local(options.timezone, date, options.time).filter(|at| *at > now),
+        None => {
+            let today = now.with_timezone(&options.timezone).date_naive();
+            today
+                .iter_days()
+                .take(8)
+                .filter(|date| options.day.is_none_or(|day| date.weekday() == day))
+                .filter_map(|date| local(options.timezone, date, options.time))
+                .find(|at| *at > now)
+        }
+    };
+    let Some(first_run) = first_run else {
+        return send_ephemeral_message(ctx, cmd, "That moment has already passed.").await;
+    };
+
+    // Use the same setup wizard as a regular readycheck for who to include and for how long
+    let (duration, roles, users, voice_channel) = match setup(handler, &ctx, &cmd, "Schedule readycheck!").await? {
+        SetupResult::Invalid(e) => {
+            cmd.edit_response(&ctx, EditInteractionResponse::new().content(e).components(vec![])).await?;
+            return Ok(());
+        }
+        SetupResult::Valid { duration, roles, users, voice_channel } => (duration, roles, users, voice_channel),
+    };
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let model = readycheck_schedule::ActiveModel {
+        id: Default::default(),
+        server_id: Set(guild_id.get() as i64),
+        channel_id: Set(cmd.channel_id.get() as i64),
+        creator_id: Set(cmd.user.id.get() as i64),
+        roles: Set(serde_json::to_value(&roles)?),
+        users: Set(serde_json::to_value(&users)?),
+        duration: Set(duration.as_secs() as i32),
+        reminder: Set(options.reminder.map(|reminder| (reminder.as_secs() / 60) as i32)),
+        time: Set(options.time),
+        timezone: Set(options.timezone.name().to_string()),
+        repeat: Set(options.repeat.as_str().to_string()),
+        next_run: Set(first_run.fixed_offset()),
+        voice_channel_id: Set(voice_channel.map(|channel| channel.get() as i64)),
+        leave_not_ready: Set(options.leave_not_ready),
+    }
+    .insert(&db)
+    .await?;
+    info!("{} scheduled readycheck {} in guild {guild_id}", cmd.user.name, model.id);
+
+    let repeats = match options.repeat {
+        Repeat::Once => String::new(),
+        repeat => format!(", repeating {}", repeat.as_str()),
+    };
+    cmd.edit_response(
+        &ctx,
+        EditInteractionResponse::new()
+            .content(format!("Scheduled readycheck #{} for <t:{}:F>{repeats}.", model.id, first_run.timestamp()))
+            .components(vec![]),
+    )
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn handle_list(ctx: Context, cmd: CommandInteraction) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let schedules = ReadycheckSchedule::find()
+        .filter(readycheck_schedule::Column::ServerId.eq(guild_id.get()))
+        .order_by_asc(readycheck_schedule::Column::NextRun)
+        .all(&db)
+        .await?;
+    if schedules.is_empty() {
+        return send_ephemeral_message(ctx, cmd, "There are no scheduled readychecks.").await;
+    }
+
+    let mut lines = Vec::new();
+    for schedule in schedules {
+        let roles: Vec<RoleId> = serde_json::from_value(schedule.roles)?;
+        let users: Vec<UserId> = serde_json::from_value(schedule.users)?;
+        let mentions = roles
+            .iter()
+            .map(|r| r.mention().to_string())
+            .chain(users.iter().map(|u| u.mention().to_string()))
+            .chain(schedule.voice_channel_id.map(|c| format!("everyone in {}", ChannelId::new(c as u64).mention())))
+            .collect::<Vec<_>>()
+            .join(" ");
+        lines.push(format!(
+            "**#{}** <t:{}:F> in {}, {} ({}) for {mentions}",
+            schedule.id,
+            schedule.next_run.timestamp(),
+            ChannelId::new(schedule.channel_id as u64).mention(),
+            schedule.repeat,
+            schedule.timezone,
+        ));
+    }
+
+    send_ephemeral_message(ctx, cmd, &truncate(&lines.join("\n"), MAX_MESSAGE_LENGTH)).await
+}
+
+pub(super) async fn handle_unschedule(
+    ctx: Context,
+    cmd: CommandInteraction,
+    options: &[CommandDataOption],
+) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+    let Some(id) = options.iter().find_map(|option| match option.value {
+        CommandDataOptionValue::Integer(id) if option.name == "id" => Some(id),
+        _ => None,
+    }) else {
+        return send_ephemeral_message(ctx, cmd, "No id passed.").await;
+    };
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let deleted = ReadycheckSchedule::delete_many()
+        .filter(readycheck_schedule::Column::Id.eq(id))
+        .filter(readycheck_schedule::Column::ServerId.eq(guild_id.get()))
+        .exec(&db)
+        .await?
+        .rows_affected;
+
+    if deleted == 0 {
+        return send_ephemeral_message(ctx, cmd, "There is no scheduled readycheck with that id.").await;
+    }
+    info!("{} removed scheduled readycheck {id} in guild {guild_id}", cmd.user.name);
+    send_ephemeral_message(ctx, cmd, &format!("Removed scheduled readycheck #{id}.")).await
+}
+
+/// Suggests the scheduled readychecks of this server, soonest first.
+pub(crate) async fn handle_autocomplete(ctx: Context, cmd: CommandInteraction) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let schedules = ReadycheckSchedule::find()
+        .filter(readycheck_schedule::Column::ServerId.eq(guild_id.get()))
+        .order_by_asc(readycheck_schedule::Column::NextRun)
+        .limit(MAX_SUGGESTIONS)
+        .all(&db)
+        .await?;
+
+    let mut response = CreateAutocompleteResponse::new();
+    for schedule in schedules {
+        let timezone: Tz = schedule.timezone.parse().unwrap_or(Tz::UTC);
+        let next_run = schedule.next_run.with_timezone(&timezone);
+        response = response.add_int_choice(
+            format!(
+                "#{} {} {} ({})",
+                schedule.id,
+                next_run.format("%a %Y-%m-%d %H:%M"),
+                schedule.timezone,
+                schedule.repeat
+            ),
+            schedule.id,
+        );
+    }
+
+    cmd.create_response(ctx, CreateInteractionResponse::Autocomplete(response)).await?;
+    Ok(())
+}
+
+/// Starts scheduled readychecks once they're due. Should only ever be running once.
+pub(super) async fn run(ctx: Context) {
+    loop {
+        if let Err(e) = run_due(&ctx).await {
+            error!("Could not run scheduled readychecks: {e}");
+        }
+        sleep(POLL_INTERVAL).await;
+    }
+}
+
+async fn run_due(ctx: &Context) -> Result<()> {
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let now = Utc::now();
+    let due = ReadycheckSchedule::find().filter(readycheck_schedule::Column::NextRun.lte(now)).all(&db).await?;
+
+    for schedule in due {
+        // If we were offline for the entire readycheck, there is no point in starting it anymore
+        let deadline = schedule.next_run.to_utc() + Duration::from_secs(schedule.duration.max(0) as u64);
+        if deadline > now {
+            if let Err(e) = run_schedule(ctx, &schedule).await {
+                error!("Could not start scheduled readycheck {}: {e}", schedule.id);
+            }
+        } else {
+            info!("Skipping scheduled readycheck {} as its deadline has passed", schedule.id);
+        }
+
+        match next_run(&schedule, now) {
+            Some(next_run) => {
+                let mut active = schedule.into_active_model();
+                active.next_run = Set(next_run.fixed_offset());
+                active.update(&db).await?;
+            }
+            None => {
+                ReadycheckSchedule::delete_by_id(schedule.id).exec(&db).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn run_schedule(ctx: &Context, schedule: &readycheck_schedule::Model) -> Result<()> {
+    let request = Request {
+        guild_id: GuildId::new(schedule.server_id as u64),
+        channel_id: ChannelId::new(schedule.channel_id as u64),
+        initiator: UserId::new(schedule.creator_id as u64),
+        duration: Duration::from_secs(schedule.duration.max(0) as u64),
+        reminder: schedule.reminder.map(|minutes| Duration::from_secs(minutes.max(0) as u64 * 60)),
+        roles: serde_json::from_value(schedule.roles.clone())?,
+        users: serde_json::from_value(schedule.users.clone())?,
+        voice_channel: schedule.voice_channel_id.map(|channel| ChannelId::new(channel as u64)),
+        leave_not_ready: schedule.leave_not_ready,
+    };
+
+    if let Some(e) = start(ctx, request).await? {
+        info!("Scheduled readycheck {} was not started: {e}", schedule.id);
+    }
+    Ok(())
+}
+
+/// The first run of a repeating schedule after the given moment, at the same local time. None if it doesn't repeat.
+fn next_run(schedule: &readycheck_schedule::Model, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let interval = Repeat::parse(&schedule.repeat)?.interval()?;
+    let timezone: Tz = schedule.timezone.parse().ok()?;
+    let last_run = schedule.next_run.with_timezone(&timezone).date_naive();
+
+    successors(Some(last_run), |date| date.checked_add_days(interval))
+        .skip(1)
+        .filter_map(|date| local(timezone, date, schedule.time))
+        .find(|at| *at > after)
+}
+
+/// Converts a local date and time into UTC, or None if that moment doesn't exist (like during a DST switch).
+fn local(timezone: Tz, date: NaiveDate, time: NaiveTime) -> Option<DateTime<Utc>> {
+    timezone.from_local_datetime(&date.and_time(time)).earliest().map(|at| at.to_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, NaiveTime, Utc};
+    use serde_json::json;
+
+    use entity::readycheck_schedule;
+
+    use super::next_run;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    fn schedule(repeat: &str, next_run: &str) -> readycheck_schedule::Model {
+        readycheck_schedule::Model {
+            id: 1,
+            server_id: 1,
+            channel_id: 1,
+            creator_id: 1,
+            roles: json!([]),
+            users: json!([]),
+            duration: 5,
+            reminder: None,
+            time: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            timezone: "Europe/Amsterdam".to_string(),
+            repeat: repeat.to_string(),
+            next_run: at(next_run).fixed_offset(),
+            voice_channel_id: None,
+            leave_not_ready: false,
+        }
+    }
+
+    #[test]
+    fn doesnt_repeat_once() {
+        assert_eq!(next_run(&schedule("once", "2026-06-01T07:00:00Z"), at("2026-06-01T07:00:00Z")), None);
+    }
+
+    #[test]
+    fn repeats_daily_and_weekly() {
+        let after = at("2026-06-01T07:00:00Z");
+        assert_eq!(next_run(&schedule("daily", "2026-06-01T07:00:00Z"), after), Some(at("2026-06-02T07:00:00Z")));
+        assert_eq!(next_run(&schedule("weekly", "2026-06-01T07:00:00Z"), after), Some(at("2026-06-08T07:00:00Z")));
+    }
+
+    #[test]
+    fn skips_the_runs_that_were_missed() {
+        let daily = schedule("daily", "2026-06-01T07:00:00Z");
+        assert_eq!(next_run(&daily, at("2026-06-10T00:00:00Z")), Some(at("2026-06-10T07:00:00Z")));
+    }
+
+    #[test]
+    fn keeps_the_local_time_across_dst() {
+        // 9:00 is 8:00 UTC in winter time, and 7:00 UTC in summer time
+        let weekly = schedule("weekly", "2026-03-26T08:00:00Z");
+        assert_eq!(next_run(&weekly, at("2026-03-26T08:00:00Z")), Some(at("2026-04-02T07:00:00Z")));
+    }
+}
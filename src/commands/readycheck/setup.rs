@@ -0,0 +1,224 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use serenity::{
+    all::{
+        ActionRowComponent, ChannelId, ChannelType, CommandInteraction, ComponentInteraction,
+        ComponentInteractionDataKind, InputTextStyle, ModalInteraction, RoleId, UserId,
+    },
+    builder::{
+        CreateActionRow, CreateButton, CreateInputText, CreateInteractionResponse, CreateInteractionResponseMessage,
+        CreateModal, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption,
+    },
+    client::Context,
+};
+use tokio::{
+    select,
+    time::{sleep_until, Instant},
+};
+
+use crate::{
+    handler::Handler,
+    util::{format_duration, parse_duration},
+};
+
+// The longest a readycheck can run for when picking a custom duration
+pub(super) const MAX_DURATION: Duration = Duration::from_secs(24 * 60 * 60);
+const PROMPT: &str = "Select the roles/users and/or the voice channel you wish to include in this readycheck.";
+
+pub(super) enum SetupResult {
+    Valid { duration: Duration, roles: Vec<RoleId>, users: Vec<UserId>, voice_channel: Option<ChannelId> },
+    Invalid(&'static str),
+}
+
+pub(super) async fn setup(
+    handler: &Handler,
+    ctx: &Context,
+    cmd: &CommandInteraction,
+    submit_label: &str,
+) -> Result<SetupResult> {
+    let setup_end_time = Instant::now() + Duration::from_secs(60);
+    let mention_id = format!("rou_{}_mention", cmd.id);
+    let voice_id = format!("rou_{}_voice", cmd.id);
+    let timeout_id = format!("rou_{}_timeout", cmd.id);
+    let submit_id = format!("rou_{}_submit", cmd.id);
+    let modal_id = format!("rou_{}_modal", cmd.id);
+
+    let mut recv = handler.subscribe_to_component_interactions();
+    let mut modal_recv = handler.subscribe_to_modal_interactions();
+
+    // First we send a prompt to the user, asking them to choose who to ping.
+    cmd.create_response(
+        ctx,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().ephemeral(true).content(PROMPT).components(vec![
+                CreateActionRow::SelectMenu(
+                    CreateSelectMenu::new(
+                        mention_id.as_str(),
+                        CreateSelectMenuKind::Mentionable { default_roles: None, default_users: None },
+                    )
+                    .min_values(1)
+                    .max_values(25),
+                ),
+                CreateActionRow::SelectMenu(
+                    CreateSelectMenu::new(
+                        voice_id.as_str(),
+                        CreateSelectMenuKind::Channel {
+                            channel_types: Some(vec![ChannelType::Voice, ChannelType::Stage]),
+                            default_channels: None,
+                        },
+                    )
+                    .placeholder("Everyone in a voice channel")
+                    .min_values(0)
+                    .max_values(1),
+                ),
+                CreateActionRow::SelectMenu(CreateSelectMenu::new(
+                    timeout_id.as_str(),
+                    CreateSelectMenuKind::String {
+                        options: vec![
+                            CreateSelectMenuOption::new("1 minute", "60").default_selection(true),
+                            CreateSelectMenuOption::new("5 minutes", "300"),
+                            CreateSelectMenuOption::new("1 hour", "3600"),
+                            CreateSelectMenuOption::new("Custom…", "custom"),
+                        ],
+                    },
+                )),
+                CreateActionRow::Buttons(vec![CreateButton::new(submit_id.as_str()).label(submit_label)]),
+            ]),
+        ),
+    )
+    .await?;
+
+    let mut duration = Duration::from_secs(60);
+    let mut roles = Vec::new();
+    let mut users = Vec::new();
+    let mut voice_channel = None;
+
+    loop {
+        let (interaction_ctx, interaction): (Context, ComponentInteraction) = select! {
+            interaction = recv.recv() => {
+                match interaction {
+                    Ok(interaction) => interaction,
+                    Err(e) => {
+                        error!("Error receiving interaction in readycheck setup loop: {e}");
+                        continue;
+                    }
+                }
+            },
+            modal = modal_recv.recv() => {
+                match modal {
+                    Ok((modal_ctx, modal)) if modal.data.custom_id == modal_id => {
+                        if let Some(custom) = custom_duration(&modal_ctx, &modal).await? {
+                            duration = custom;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!("Error receiving modal in readycheck setup loop: {e}"),
+                }
+                continue;
+            },
+            _ = sleep_until(setup_end_time) => {
+                return Ok(SetupResult::Invalid("Readycheck setup time expired"));
+            }
+        };
+
+        // Figure out which information we received
+        match interaction.data.custom_id.as_str() {
+            custom_id if custom_id == mention_id.as_str() => {
+                let ComponentInteractionDataKind::MentionableSelect { values } = &interaction.data.kind else {
+                    return Ok(SetupResult::Invalid("Could not parse users/roles."));
+                };
+
+                roles.clear();
+                users.clear();
+
+                for generic_id in values {
+                    // First we try the role ID, as we can do this from the cache
+                    let role_attempt = RoleId::new(generic_id.get());
+
+                    if cmd
+                        .guild_id // If we have a guild ID
+                        .and_then(|g| interaction_ctx.cache.guild(g))
+                        .map_or_else(Default::default, |g| g.roles.contains_key(&role_attempt))
+                    {
+                        // ID is a known role
+                        roles.push(role_attempt);
+                    } else {
+                        users.push(UserId::new(generic_id.get()));
+                    }
+                }
+
+                interaction.create_response(interaction_ctx, CreateInteractionResponse::Acknowledge).await?;
+            }
+            custom_id if custom_id == voice_id.as_str() => {
+                let ComponentInteractionDataKind::ChannelSelect { values } = &interaction.data.kind else {
+                    return Ok(SetupResult::Invalid("Could not parse voice channel."));
+                };
+
+                voice_channel = values.first().copied();
+
+                interaction.create_response(interaction_ctx, CreateInteractionResponse::Acknowledge).await?;
+            }
+            custom_id if custom_id == timeout_id.as_str() => {
+                let ComponentInteractionDataKind::StringSelect { values } = &interaction.data.kind else {
+                    return Ok(SetupResult::Invalid("Could not parse duration."));
+                };
+
+                // A custom duration is entered through a modal, which we pick up above once it's submitted
+                if values.first().is_some_and(|value| value == "custom") {
+                    let modal = CreateModal::new(modal_id.as_str(), "Readycheck duration").components(vec![
+                        CreateActionRow::InputText(
+                            CreateInputText::new(InputTextStyle::Short, "Duration", "duration")
+                                .placeholder("For example 10m or 1h30m")
+                                .max_length(16),
+                        ),
+                    ]);
+                    interaction.create_response(interaction_ctx, CreateInteractionResponse::Modal(modal)).await?;
+                    continue;
+                }
+
+                let Some(selected_time): Option<Duration> =
+                    values.first().and_then(|s| s.parse().ok()).map(Duration::from_secs)
+                else {
+                    return Ok(SetupResult::Invalid("Could not parse duration."));
+                };
+
+                duration = selected_time;
+
+                interaction.create_response(interaction_ctx, CreateInteractionResponse::Acknowledge).await?;
+            }
+            custom_id if custom_id == submit_id.as_str() => break,
+            _ => continue,
+        }
+    }
+
+    if roles.is_empty() && users.is_empty() && voice_channel.is_none() {
+        return Ok(SetupResult::Invalid("No users, roles or voice channel selected."));
+    }
+
+    Ok(SetupResult::Valid { duration, users, roles, voice_channel })
+}
+
+/// Reads the duration entered in the custom duration modal, and tells the user whether we could use it.
+async fn custom_duration(ctx: &Context, modal: &ModalInteraction) -> Result<Option<Duration>> {
+    let input = modal.data.components.iter().flat_map(|row| row.components.iter()).find_map(|c| match c {
+        ActionRowComponent::InputText(input) => input.value.clone(),
+        _ => None,
+    });
+    let custom = input.as_deref().and_then(parse_duration).filter(|custom| *custom <= MAX_DURATION);
+
+    let content = match custom {
+        Some(custom) => format!("{PROMPT}\nThe readycheck will last {}.", format_duration(custom)),
+        None => format!(
+            "{PROMPT}\nCould not use that duration, try something like 10m or 1h30m (up to {}).",
+            format_duration(MAX_DURATION)
+        ),
+    };
+    modal
+        .create_response(
+            ctx,
+            CreateInteractionResponse::UpdateMessage(CreateInteractionResponseMessage::new().content(content)),
+        )
+        .await?;
+    Ok(custom)
+}
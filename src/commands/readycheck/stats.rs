@@ -0,0 +1,194 @@
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::Result;
+use sea_orm::{
+    prelude::DateTimeWithTimeZone, ColumnTrait, DatabaseConnection, EntityTrait, FromQueryResult, QueryFilter,
+    QueryOrder, QuerySelect,
+};
+use serde::Serialize;
+use serenity::{
+    all::{CommandDataOption, CommandDataOptionValue, CommandInteraction},
+    builder::{CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage},
+    client::Context,
+    model::{
+        id::{GuildId, UserId},
+        Colour,
+    },
+};
+
+use entity::{prelude::Readycheck, readycheck};
+
+use crate::{
+    commands::{
+        readycheck::{
+            lifecycle::{self, Outcome},
+            Participant,
+            ReadyState::Ready,
+        },
+        send_ephemeral_message,
+    },
+    util::{format_duration, DatabaseTypeMapKey},
+};
+
+// Discord doesn't allow longer embed descriptions than this
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+// How many members we list at most in the overview
+const MAX_MEMBERS: usize = 25;
+
+/// How someone has been doing in the readychecks they were part of.
+#[derive(Serialize)]
+pub(crate) struct MemberStats {
+    pub(crate) user_id: UserId,
+    pub(crate) name: String,
+    pub(crate) readychecks: u32,
+    pub(crate) responded: u32,
+    pub(crate) ready: u32,
+    pub(crate) response_rate: u32,
+    // The average amount of seconds it took them to become ready, if they ever did
+    pub(crate) average_time_to_ready: Option<u64>,
+}
+
+/// A readycheck that has ended, as shown in its history.
+#[derive(Serialize, FromQueryResult)]
+pub(crate) struct FinishedReadycheck {
+    pub(crate) id: i64,
+    pub(crate) channel_id: i64,
+    pub(crate) ended_at: DateTimeWithTimeZone,
+    pub(crate) outcome: Option<String>,
+    #[serde(skip)]
+    participants: serde_json::Value,
+}
+
+impl FinishedReadycheck {
+    /// The amount of participants that were ready, and the amount of participants in total.
+    pub(crate) fn ready_count(&self) -> Result<(usize, usize)> {
+        let participants: Vec<Participant> = serde_json::from_value(self.participants.clone())?;
+        Ok((participants.iter().filter(|p| p.state == Ready).count(), participants.len()))
+    }
+}
+
+/// The most recently ended readychecks of a guild, newest first.
+pub(crate) async fn history(db: &DatabaseConnection, guild_id: GuildId, limit: u64) -> Result<Vec<FinishedReadycheck>> {
+    Ok(Readycheck::find()
+        .select_only()
+        .column(readycheck::Column::Id)
+        .column(readycheck::Column::ChannelId)
+        .column(readycheck::Column::EndedAt)
+        .column(readycheck::Column::Outcome)
+        .column(readycheck::Column::Participants)
+        .filter(readycheck::Column::ServerId.eq(guild_id.get()))
+        .filter(readycheck::Column::EndedAt.is_not_null())
+        .order_by_desc(readycheck::Column::Id)
+        .limit(limit)
+        .into_model()
+        .all(db)
+        .await?)
+}
+
+/// Goes through every readycheck of a guild that ran its course, and sums up how every member did.
+/// Cancelled readychecks aren't counted, as nobody got the chance to respond to those.
+pub(crate) async fn member_stats(db: &DatabaseConnection, guild_id: GuildId) -> Result<Vec<MemberStats>> {
+    let readychecks = Readycheck::find()
+        .filter(readycheck::Column::ServerId.eq(guild_id.get()))
+        .filter(readycheck::Column::EndedAt.is_not_null())
+        .filter(readycheck::Column::Outcome.is_null().or(readycheck::Column::Outcome.ne(Outcome::Cancelled.as_str())))
+        .order_by_asc(readycheck::Column::Id)
+        .all(db)
+        .await?;
+
+    let mut stats: HashMap<UserId, (MemberStats, u64)> = HashMap::new();
+    for model in readychecks {
+        let started_at = model.started_at.map(|started_at| started_at.to_utc());
+        for participant in lifecycle::participants(&model)? {
+            let (member, total_time_to_ready) = stats.entry(participant.user_id).or_insert_with(|| {
+                let member = MemberStats {
+                    user_id: participant.user_id,
+                    name: String::new(),
+                    readychecks: 0,
+                    responded: 0,
+                    ready: 0,
+                    response_rate: 0,
+                    average_time_to_ready: None,
+                };
+                (member, 0)
+            });
+
+            // We go through the readychecks oldest first, so this ends up as their most recent name
+            member.name = participant.name;
+            member.readychecks += 1;
+            if participant.responded_at.is_some() {
+                member.responded += 1;
+            }
+            if participant.state == Ready {
+                member.ready += 1;
+                if let (Some(started_at), Some(ready_at)) = (started_at, participant.ready_at) {
+                    *total_time_to_ready += (ready_at - started_at).num_seconds().max(0) as u64;
+                }
+            }
+        }
+    }
+
+    let mut stats: Vec<MemberStats> = stats
+        .into_values()
+        .map(|(mut member, total_time_to_ready)| {
+            member.response_rate = member.responded * 100 / member.readychecks.max(1);
+            member.average_time_to_ready = (member.ready > 0).then(|| total_time_to_ready / member.ready as u64);
+            member
+        })
+        .collect();
+    stats.sort_by(|a, b| b.readychecks.cmp(&a.readychecks).then_with(|| a.name.cmp(&b.name)));
+    Ok(stats)
+}
+
+pub(super) async fn handle_stats(ctx: Context, cmd: CommandInteraction, options: &[CommandDataOption]) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+    let user = options.iter().find_map(|option| match option.value {
+        CommandDataOptionValue::User(user) if option.name == "user" => Some(user),
+        _ => None,
+    });
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let stats = member_stats(&db, guild_id).await?;
+
+    let lines: Vec<String> = match user {
+        Some(user) => match stats.iter().find(|member| member.user_id == user) {
+            Some(member) => vec![describe(member)],
+            None => return send_ephemeral_message(ctx, cmd, "They haven't been part of any readychecks yet.").await,
+        },
+        None => stats.iter().take(MAX_MEMBERS).map(describe).collect(),
+    };
+    if lines.is_empty() {
+        return send_ephemeral_message(ctx, cmd, "There haven't been any readychecks yet.").await;
+    }
+
+    let mut description = String::new();
+    for line in lines {
+        if description.chars().count() + line.chars().count() + 1 > MAX_DESCRIPTION_LENGTH {
+            break;
+        }
+        description.push_str(&line);
+        description.push('\n');
+    }
+
+    cmd.create_response(
+        &ctx,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().embed(
+                CreateEmbed::new().title("Readycheck stats").colour(Colour::FABLED_PINK).description(description),
+            ),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+fn describe(member: &MemberStats) -> String {
+    let time_to_ready = match member.average_time_to_ready {
+        Some(seconds) => format!(", ready after {} on average", format_duration(Duration::from_secs(seconds))),
+        None => String::new(),
+    };
+    format!(
+        "**{}**: {} readychecks, responded to {}%, ready for {}{time_to_ready}",
+        member.name, member.readychecks, member.response_rate, member.ready
+    )
+}
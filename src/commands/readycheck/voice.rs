@@ -0,0 +1,58 @@
+use anyhow::Result;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, QuerySelect,
+};
+use serenity::{client::Context, model::voice::VoiceState};
+
+use entity::{prelude::Readycheck, readycheck};
+
+use crate::{
+    commands::readycheck::{lifecycle, ReadyState::NotReady},
+    util::DatabaseTypeMapKey,
+};
+
+/// Marks people as not ready when they leave the voice channel of a readycheck that asked for it.
+pub(crate) async fn voice_state_update(ctx: Context, old: Option<VoiceState>, new: VoiceState) -> Result<()> {
+    let Some(guild_id) = new.guild_id else { return Ok(()) };
+    // We only care about people leaving (or moving out of) a voice channel, which we can only tell with the old state
+    let Some(left) = old.and_then(|old| old.channel_id) else { return Ok(()) };
+    if new.channel_id == Some(left) {
+        return Ok(());
+    }
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let ids: Vec<i64> = Readycheck::find()
+        .select_only()
+        .column(readycheck::Column::Id)
+        .filter(readycheck::Column::ServerId.eq(guild_id.get()))
+        .filter(readycheck::Column::VoiceChannelId.eq(left.get()))
+        .filter(readycheck::Column::LeaveNotReady.eq(true))
+        .filter(readycheck::Column::EndedAt.is_null())
+        .into_tuple()
+        .all(&db)
+        .await?;
+
+    for id in ids {
+        // The readycheck could have changed or ended before we got its lock
+        let _guard = lifecycle::lock(id).await;
+        let model = match Readycheck::find_by_id(id).one(&db).await? {
+            Some(model) if model.ended_at.is_none() => model,
+            _ => continue,
+        };
+        let mut participants = lifecycle::participants(&model)?;
+        let Some(participant) = participants.iter_mut().find(|p| p.user_id == new.user_id) else { continue };
+        if participant.state == NotReady {
+            continue;
+        }
+
+        participant.state = NotReady;
+        participant.eta = None;
+        participant.ready_at = None;
+
+        let mut active = model.into_active_model();
+        active.participants = Set(serde_json::to_value(&participants)?);
+        let model = active.update(&db).await?;
+        lifecycle::refresh(&ctx, &db, &model, &participants).await?;
+    }
+    Ok(())
+}
@@ -1,65 +1,168 @@
-use std::{collections::VecDeque, sync::OnceLock};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::OnceLock,
+    time::Instant,
+};
 
 use anyhow::{anyhow, Result};
 use rand::{rng, seq::IteratorRandom};
-use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QuerySelect};
+use sea_orm::{EntityTrait, QuerySelect};
 use serenity::{
-    all::{Command, CommandInteraction},
-    builder::CreateCommand,
+    all::{Command, CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType},
+    builder::{CreateCommand, CreateCommandOption},
     client::Context,
+    model::id::GuildId,
 };
 use tokio::sync::Mutex;
 
 use entity::{prelude::Quote, quote};
 
-use crate::{commands::send_ephemeral_message, quote::post_quote, util::DatabaseTypeMapKey};
+use crate::{
+    commands::send_ephemeral_message,
+    quote::{post_quote, QuoteFilter},
+    util::{parse_date, DatabaseTypeMapKey},
+};
+
+// How many filters we remember recent quotes for, the least recently used one makes way for a new one
+const MAX_BLACKLISTS: usize = 100;
+
+// Recently posted quotes, kept apart for every filter so a narrow one can't wipe out what a broad one remembers
+static RANDOM_BLACKLISTS: OnceLock<Mutex<HashMap<(GuildId, QuoteFilter), Blacklist>>> = OnceLock::new();
 
-static RANDOM_BLACKLIST: OnceLock<Mutex<VecDeque<i64>>> = OnceLock::new();
+struct Blacklist {
+    ids: VecDeque<i64>,
+    last_used: Instant,
+}
 
 pub(super) async fn register(ctx: &Context) -> Result<()> {
     Command::create_global_command(
         ctx,
-        CreateCommand::new("rquote").description("Posts a random quote").dm_permission(false),
+        CreateCommand::new("rquote")
+            .description("Posts a random quote, optionally narrowed down by any combination of filters")
+            .dm_permission(false)
+            .add_option(CreateCommandOption::new(CommandOptionType::User, "user", "Only quotes by this user"))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::Channel,
+                "channel",
+                "Only quotes posted in this channel",
+            ))
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "keyword", "Only quotes containing this keyword")
+                    .set_autocomplete(true),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "before",
+                "Only quotes from before this date (YYYY-MM-DD)",
+            ))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "after",
+                "Only quotes from this date (YYYY-MM-DD) onwards",
+            ))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "has_image",
+                "Only quotes with (or without) an image",
+            )),
     )
     .await?;
     Ok(())
 }
 
 pub(super) async fn handle_command(ctx: Context, cmd: CommandInteraction) -> Result<()> {
+    let filter = match parse_filter(&cmd.data.options) {
+        Ok(filter) => filter,
+        Err(e) => return send_ephemeral_message(ctx, cmd, e).await,
+    };
+
+    let not_found = if filter.is_empty() {
+        "Could not find any random quotes, do none exist?"
+    } else {
+        "Could not find any random quotes matching those filters, do none exist?"
+    };
+    post_random_quote(ctx, cmd, filter, not_found).await
+}
+
+fn parse_filter(options: &[CommandDataOption]) -> Result<QuoteFilter, &'static str> {
+    let mut filter = QuoteFilter::default();
+    for option in options {
+        match (option.name.as_str(), &option.value) {
+            ("user", CommandDataOptionValue::User(user)) => filter.user = Some(*user),
+            ("channel", CommandDataOptionValue::Channel(channel)) => filter.channel = Some(*channel),
+            ("keyword", CommandDataOptionValue::String(keyword)) => filter.keyword = Some(keyword.to_owned()),
+            ("before", CommandDataOptionValue::String(date)) => {
+                filter.before = Some(parse_date(date).ok_or("Could not parse the before date, use YYYY-MM-DD.")?)
+            }
+            ("after", CommandDataOptionValue::String(date)) => {
+                filter.after = Some(parse_date(date).ok_or("Could not parse the after date, use YYYY-MM-DD.")?)
+            }
+            ("has_image", CommandDataOptionValue::Boolean(has_image)) => filter.has_image = Some(*has_image),
+            _ => return Err("Received an unknown filter."),
+        }
+    }
+    Ok(filter)
+}
+
+/// Picks a random quote matching the filter and posts it as a response to the command.
+/// Recently posted quotes are avoided where possible, to prevent repeats.
+pub(super) async fn post_random_quote(
+    ctx: Context,
+    cmd: CommandInteraction,
+    filter: QuoteFilter,
+    not_found: &str,
+) -> Result<()> {
     let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
     let Some(guild_id) = cmd.guild_id else {
         return send_ephemeral_message(ctx, cmd, "This command can only be used in servers.").await;
     };
 
     // First we find a collection of IDs we can choose from
-    let ids: Vec<i64> = Quote::find()
-        .select_only()
-        .column(quote::Column::Id)
-        .filter(quote::Column::ServerId.eq(guild_id.get()))
-        .into_tuple()
-        .all(&db)
-        .await?;
-
-    // Then we get the blacklist, to avoid quote repeats
-    let mut blacklist = RANDOM_BLACKLIST.get_or_init(Default::default).lock().await;
-
-    // Then we filter our id list and choose a random quote
-    let Some(chosen_random) = ids.iter().filter(|v| !blacklist.contains(*v)).choose(&mut rng()) else {
-        drop(blacklist); // Drop our blacklist reference early
-        return send_ephemeral_message(ctx, cmd, "Could not find any random quotes, do none exist?").await;
+    let ids: Vec<i64> =
+        filter.apply(guild_id, Quote::find().select_only().column(quote::Column::Id)).into_tuple().all(&db).await?;
+
+    // Then we get the blacklist of this filter, to avoid quote repeats
+    let key = (guild_id, filter);
+    let mut blacklists = RANDOM_BLACKLISTS.get_or_init(Default::default).lock().await;
+    let blacklist = blacklists.get(&key);
+
+    // Then we filter our id list and choose a random quote, only allowing repeats if the filter leaves us no choice
+    let chosen_random = ids
+        .iter()
+        .filter(|v| !blacklist.is_some_and(|blacklist| blacklist.ids.contains(*v)))
+        .choose(&mut rng())
+        .or_else(|| ids.iter().choose(&mut rng()))
+        .copied();
+    let Some(chosen_random) = chosen_random else {
+        drop(blacklists); // Drop our blacklist reference early
+        return send_ephemeral_message(ctx, cmd, not_found).await;
     };
 
-    // Update our blacklist
-    blacklist.push_back(*chosen_random);
-    if blacklist.len() as f32 > (ids.len() as f32 / 10f32).floor() {
-        blacklist.pop_front();
+    // Update our blacklist, which remembers at most a tenth of the quotes the filter leaves us
+    let remember = ids.len() / 10;
+    if remember == 0 {
+        blacklists.remove(&key);
+    } else {
+        if blacklists.len() >= MAX_BLACKLISTS && !blacklists.contains_key(&key) {
+            let oldest = blacklists.iter().min_by_key(|(_, blacklist)| blacklist.last_used).map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                blacklists.remove(&oldest);
+            }
+        }
+        let blacklist =
+            blacklists.entry(key).or_insert_with(|| Blacklist { ids: VecDeque::new(), last_used: Instant::now() });
+        blacklist.last_used = Instant::now();
+        blacklist.ids.push_back(chosen_random);
+        while blacklist.ids.len() > remember {
+            blacklist.ids.pop_front();
+        }
     }
 
     // Drop our lock on the blacklist
-    drop(blacklist);
+    drop(blacklists);
 
     // And fetch the quote that belongs to that
-    let quote = Quote::find_by_id(*chosen_random).one(&db).await?;
+    let quote = Quote::find_by_id(chosen_random).one(&db).await?;
 
     match quote {
         Some(quote) => post_quote(&ctx, quote, cmd.channel_id, Some(cmd)).await,
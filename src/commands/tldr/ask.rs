@@ -0,0 +1,200 @@
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use serenity::{
+    all::{Command, CommandDataOptionValue, CommandInteraction, CommandOptionType, CreateInteractionResponseMessage},
+    builder::{CreateCommand, CreateCommandOption, CreateEmbed, CreateInteractionResponse, EditInteractionResponse},
+    client::Context,
+    model::Colour,
+};
+
+use crate::{
+    commands::{
+        edit_interaction, send_ephemeral_message,
+        tldr::{
+            limit_images, num_tokens_from_messages, privacy, replies_option, since_option, summarize, thread_option,
+            usage, Request,
+        },
+    },
+    llm::{ChatMessage, Role},
+    util::{DatabaseTypeMapKey, TLDRTypeMapKey},
+};
+
+// Discord doesn't allow longer messages, or more text in an embed description
+const MAX_ANSWER_LENGTH: usize = 2000;
+const MAX_SOURCES_LENGTH: usize = 4096;
+
+pub(crate) async fn register(ctx: &Context) -> Result<()> {
+    Command::create_global_command(
+        ctx,
+        CreateCommand::new("ask")
+            .description("Answers a question about the recent messages")
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "question", "What you'd like to know")
+                    .required(true)
+                    .max_length(500),
+            )
+            .add_option(since_option())
+            .add_option(CreateCommandOption::new(CommandOptionType::User, "user", "Only look at what this member said"))
+            .add_option(thread_option("Look at this thread or forum post instead"))
+            .add_option(replies_option("Look at the replies to this message instead, given a message link")),
+    )
+    .await?;
+    Ok(())
+}
+
+pub(crate) async fn handle_command(ctx: Context, cmd: CommandInteraction) -> Result<()> {
+    let Some(question) = cmd.data.options.iter().find_map(|option| match (option.name.as_str(), &option.value) {
+        ("question", CommandDataOptionValue::String(question)) => Some(question.trim().to_string()),
+        _ => None,
+    }) else {
+        return send_ephemeral_message(ctx, cmd, "You need to ask a question.").await;
+    };
+
+    let options = cmd.data.options.clone();
+    let mut request = match Request::parse(&ctx, &cmd, &options).await? {
+        Ok(request) => request,
+        Err(e) => return send_ephemeral_message(ctx, cmd, &e).await,
+    };
+    // Even a single message can hold the answer
+    request.min = 1;
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let permit = match request.acquire(&db, cmd.user.id).await? {
+        Ok(permit) => permit,
+        Err(e) => return send_ephemeral_message(ctx, cmd, &e).await,
+    };
+
+    let (llm, bpe) = ctx.data.read().await.get::<TLDRTypeMapKey>().unwrap().clone();
+
+    // Tell the user the bot is thinking, as language models are not super fast.
+    cmd.create_response(&ctx, CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new())).await?;
+
+    let messages = request.collect(&ctx).await?;
+    if messages.is_empty() {
+        let content = format!("There are no messages {} to answer that from.", request.describe());
+        return edit_interaction(ctx, cmd, &content).await;
+    }
+
+    let directive = ChatMessage::new(
+        Role::System,
+        format!(
+            "You are Slackerbot, a multi-purpose Discord bot that has been tasked with answering a question about the recent history of a Discord conversation. {} Every message in the history that follows starts with its number in square brackets. Only answer from what is in the history, and say so if the answer isn't in there. Cite the messages your answer is based on by their number in square brackets, like [3]. The current time is {}. Feel free to use markdown formatting in your response.",
+            request.context(),
+            cmd.data.id.created_at()
+        ),
+    );
+    let prompt = ChatMessage::new(Role::User, question.clone());
+
+    // Number the history, keeping the links around so we can point at what gets cited
+    let mut links = Vec::with_capacity(messages.len());
+    let mut history = Vec::with_capacity(messages.len());
+    for (index, message) in messages.iter().enumerate() {
+        links.push(message.link());
+        let mut message = message.to_chat_message(llm.vision);
+        message.content = format!("[{}] {}", index + 1, message.content);
+        history.push(message);
+    }
+
+    limit_images(&mut history);
+
+    // An answer has to come from a single request, so keep as much of the most recent history as fits
+    let fixed = num_tokens_from_messages(&bpe, &[directive.clone(), prompt.clone()])?;
+    let mut remaining = (llm.context_tokens - llm.response_tokens).saturating_sub(fixed);
+    let mut first = history.len();
+    while first > 0 {
+        let cost = num_tokens_from_messages(&bpe, std::slice::from_ref(&history[first - 1]))?;
+        if cost > remaining {
+            break;
+        }
+        remaining -= cost;
+        first -= 1;
+    }
+
+    privacy::audit(&db, &request, cmd.user.id, "ask", &messages[first..]).await?;
+    let mut conversation = vec![directive];
+    conversation.extend(history.drain(first..));
+    conversation.push(prompt);
+    let mut tokens_used = 0;
+    let answer = summarize::complete(&llm, &bpe, &conversation, &mut tokens_used).await?;
+
+    let sources = sources(&answer, &links[first..], first);
+    let mut content = format!("> {question}\n{answer}");
+    if content.chars().count() > MAX_ANSWER_LENGTH {
+        content = content.chars().take(MAX_ANSWER_LENGTH - 1).collect();
+        content.push('…');
+    }
+    let mut response = EditInteractionResponse::new().content(content);
+    if let Some(sources) = sources {
+        response = response.embed(CreateEmbed::new().title("Sources").colour(Colour::FABLED_PINK).description(sources));
+    }
+    cmd.edit_response(&ctx, response).await?;
+
+    // Take what we actually used out of the buckets, and keep track of it for the budget
+    permit.spend(tokens_used);
+    usage::record(&db, request.guild_id, cmd.user.id, tokens_used).await
+}
+
+/// Links the messages cited in the answer, which are numbered from offset + 1.
+fn sources(answer: &str, links: &[String], offset: usize) -> Option<String> {
+    let cited: BTreeSet<usize> =
+        citations(answer).into_iter().filter(|number| *number > offset && *number <= offset + links.len()).collect();
+
+    let mut sources = String::new();
+    for number in cited {
+        let line = format!("[\\[{number}\\]]({})\n", links[number - offset - 1]);
+        if sources.len() + line.len() > MAX_SOURCES_LENGTH {
+            break;
+        }
+        sources.push_str(&line);
+    }
+    (!sources.is_empty()).then_some(sources)
+}
+
+/// Finds every [n], or [n, m] when the model cites several messages at once.
+fn citations(answer: &str) -> Vec<usize> {
+    let mut numbers = Vec::new();
+    for part in answer.split('[').skip(1) {
+        let Some((inside, _)) = part.split_once(']') else { continue };
+        let cited: Option<Vec<usize>> = inside.split(',').map(|number| number.trim().parse().ok()).collect();
+        numbers.extend(cited.unwrap_or_default());
+    }
+    numbers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{citations, sources};
+    use crate::llm::{ChatMessage, Llm, Role};
+
+    #[test]
+    fn finds_single_and_combined_citations() {
+        assert_eq!(citations("It was decided [2], after some back and forth [3, 5]."), vec![2, 3, 5]);
+        assert_eq!(citations("Nothing [here], or [1, x], or [4"), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn only_links_citations_of_messages_that_were_sent() {
+        let links = vec!["link 3".to_string(), "link 4".to_string()];
+        assert_eq!(
+            sources("See [1], [3] and [4], and [9]", &links, 2).unwrap(),
+            "[\\[3\\]](link 3)\n[\\[4\\]](link 4)\n"
+        );
+        assert!(sources("See [1]", &links, 2).is_none());
+    }
+
+    #[tokio::test]
+    async fn cites_the_numbered_history() {
+        let llm = Llm::mock(10000, 1000);
+        let mut conversation = vec![ChatMessage::new(Role::System, "directive")];
+        conversation.extend((1..=3).map(|number| ChatMessage::new(Role::User, format!("[{number}] someone: hi"))));
+        conversation.push(ChatMessage::new(Role::User, "What did they say?"));
+
+        // The mock repeats the history back, numbers included
+        let answer = llm.complete(&conversation).await.unwrap().text;
+        assert_eq!(citations(&answer), vec![1, 2, 3]);
+        let links: Vec<String> = (1..=3).map(|number| format!("link {number}")).collect();
+        assert_eq!(sources(&answer, &links, 0).unwrap().lines().count(), 3);
+    }
+}
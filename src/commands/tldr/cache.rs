@@ -0,0 +1,206 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{FixedOffset, Utc};
+use sea_orm::{
+    sea_query::SimpleExpr, ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, QueryOrder,
+};
+use serenity::{
+    all::{ButtonStyle, ComponentInteraction},
+    builder::{
+        CreateActionRow, CreateButton, CreateInteractionResponse, CreateInteractionResponseFollowup,
+        CreateInteractionResponseMessage, EditInteractionResponse,
+    },
+    client::Context,
+    model::id::{ChannelId, GuildId, UserId},
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use entity::{prelude::TldrSummary, tldr_summary};
+
+use crate::{
+    commands::tldr::{generate, privacy, summarize::Response, usage, HistoryMessage, Request},
+    quote::truncate,
+    util::DatabaseTypeMapKey,
+};
+
+// How long a summary is handed out again for the same history
+const MAX_AGE: Duration = Duration::from_secs(60 * 60);
+// How long we keep summaries around to continue from
+const KEEP: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+// How many messages may have come or gone before a summary no longer counts as the same
+const MATERIAL_CHANGE: usize = 5;
+// Discord doesn't allow longer messages
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+const CUSTOM_ID_PREFIX: &str = "tldr_refresh_";
+
+fn matches(column: tldr_summary::Column, value: Option<u64>) -> SimpleExpr {
+    match value {
+        Some(value) => column.eq(value),
+        None => column.is_null(),
+    }
+}
+
+/// Finds a recent summary of nearly the same messages, for the same member and reply chain.
+pub(super) async fn find(
+    db: &DatabaseConnection,
+    request: &Request,
+    messages: &[HistoryMessage],
+) -> Result<Option<tldr_summary::Model>> {
+    let Some(cached) = TldrSummary::find()
+        .filter(tldr_summary::Column::ChannelId.eq(request.channel.id.get()))
+        .filter(tldr_summary::Column::CreatedAt.gte(Utc::now() - MAX_AGE))
+        .filter(matches(tldr_summary::Column::UserId, request.user.map(|user| user.get())))
+        .filter(matches(tldr_summary::Column::RepliesTo, request.replies_to.map(|id| id.get())))
+        .order_by_desc(tldr_summary::Column::CreatedAt)
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    // Count what was added at either end since, and what the summary read that we no longer do
+    let before = messages.iter().filter(|message| (message.id.get() as i64) < cached.first_message_id).count();
+    let after = messages.iter().filter(|message| (message.id.get() as i64) > cached.last_message_id).count();
+    let overlap = messages.len() - before - after;
+    let dropped = (cached.message_count.max(0) as usize).saturating_sub(overlap);
+    Ok((before + after + dropped < MATERIAL_CHANGE).then_some(cached))
+}
+
+/// The most recent summary of a channel, to continue from.
+pub(super) async fn latest(
+    db: &DatabaseConnection,
+    channel_id: ChannelId,
+    user: Option<UserId>,
+) -> Result<Option<tldr_summary::Model>> {
+    Ok(TldrSummary::find()
+        .filter(tldr_summary::Column::ChannelId.eq(channel_id.get()))
+        .filter(matches(tldr_summary::Column::UserId, user.map(|user| user.get())))
+        .filter(tldr_summary::Column::RepliesTo.is_null())
+        .order_by_desc(tldr_summary::Column::CreatedAt)
+        .one(db)
+        .await?)
+}
+
+/// Keeps a summary around, and forgets the ones of this channel that are too old to continue from.
+pub(super) async fn store(
+    db: &DatabaseConnection,
+    request: &Request,
+    messages: &[HistoryMessage],
+    text: &str,
+) -> Result<tldr_summary::Model> {
+    TldrSummary::delete_many()
+        .filter(tldr_summary::Column::ChannelId.eq(request.channel.id.get()))
+        .filter(tldr_summary::Column::CreatedAt.lt(Utc::now() - KEEP))
+        .exec(db)
+        .await?;
+
+    let first = messages.first().map(|message| message.id.get()).unwrap_or_default();
+    let last = messages.last().map(|message| message.id.get()).unwrap_or_default();
+    Ok(tldr_summary::ActiveModel {
+        id: Default::default(),
+        server_id: Set(request.guild_id.get() as i64),
+        channel_id: Set(request.channel.id.get() as i64),
+        user_id: Set(request.user.map(|user| user.get() as i64)),
+        replies_to: Set(request.replies_to.map(|id| id.get() as i64)),
+        first_message_id: Set(first as i64),
+        last_message_id: Set(last as i64),
+        message_count: Set(messages.len() as i32),
+        text: Set(text.to_string()),
+        created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
+    }
+    .insert(db)
+    .await?)
+}
+
+/// Forgets every summary of a guild, so the next tldr writes a new one instead of handing out an old one.
+pub(super) async fn forget(db: &DatabaseConnection, guild_id: GuildId) -> Result<()> {
+    TldrSummary::delete_many().filter(tldr_summary::Column::ServerId.eq(guild_id.get())).exec(db).await?;
+    Ok(())
+}
+
+/// Posts a summary with its refresh button, mentioning when it was written if it isn't new.
+pub(super) fn response(cached: &tldr_summary::Model, reused: bool) -> EditInteractionResponse {
+    let note = if reused {
+        format!("\n-# Summarized <t:{}:R>, hardly anything was said since.", cached.created_at.timestamp())
+    } else {
+        String::new()
+    };
+    let text = truncate(&cached.text, MAX_MESSAGE_LENGTH - note.chars().count());
+
+    EditInteractionResponse::new().content(format!("{text}{note}")).components(vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{CUSTOM_ID_PREFIX}{}", cached.id)).label("Refresh").style(ButtonStyle::Secondary),
+    ])])
+}
+
+pub(crate) async fn press_loop(mut recv: broadcast::Receiver<(Context, ComponentInteraction)>) {
+    loop {
+        let (ctx, interaction) = match recv.recv().await {
+            Ok(interaction) => interaction,
+            Err(e) => {
+                if matches!(e, RecvError::Closed) {
+                    return;
+                }
+
+                error!("Error receiving interaction in tldr refresh loop: {e}");
+                continue;
+            }
+        };
+
+        let Some(id) = interaction.data.custom_id.strip_prefix(CUSTOM_ID_PREFIX).and_then(|id| id.parse().ok()) else {
+            continue;
+        };
+
+        // Summarizing takes a while, and shouldn't hold up other presses
+        tokio::spawn(async move {
+            if let Err(e) = pressed(ctx, interaction, id).await {
+                error!("Could not refresh tldr: {e}");
+            }
+        });
+    }
+}
+
+/// Summarizes the same conversation again, including everything that was said since.
+async fn pressed(ctx: Context, interaction: ComponentInteraction, id: i64) -> Result<()> {
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let request = match TldrSummary::find_by_id(id).one(&db).await? {
+        Some(cached) => Request::from_cached(&ctx, &cached).await?,
+        None => None,
+    };
+    let Some(request) = request else {
+        return reply(&ctx, &interaction, "This tldr can no longer be refreshed.").await;
+    };
+
+    let permit = match request.acquire(&db, interaction.user.id).await? {
+        Ok(permit) => permit,
+        Err(e) => return reply(&ctx, &interaction, &e).await,
+    };
+    interaction.create_response(&ctx, CreateInteractionResponse::Acknowledge).await?;
+
+    let messages = request.collect(&ctx).await?;
+    if messages.is_empty() {
+        let followup =
+            CreateInteractionResponseFollowup::new().ephemeral(true).content("The messages of this tldr are gone.");
+        interaction.create_followup(&ctx, followup).await?;
+        return Ok(());
+    }
+
+    privacy::audit(&db, &request, interaction.user.id, "tldr refresh", &messages).await?;
+    let mut tokens_used = 0;
+    let summary = generate(&ctx, &Response::Component(&ctx, &interaction), &request, &messages, &mut tokens_used).await;
+
+    permit.spend(tokens_used);
+    usage::record(&db, request.guild_id, interaction.user.id, tokens_used).await?;
+
+    let cached = store(&db, &request, &messages, &summary?).await?;
+    interaction.edit_response(&ctx, response(&cached, false)).await?;
+    Ok(())
+}
+
+async fn reply(ctx: &Context, interaction: &ComponentInteraction, content: &str) -> Result<()> {
+    let message = CreateInteractionResponseMessage::new().ephemeral(true).content(content);
+    interaction.create_response(ctx, CreateInteractionResponse::Message(message)).await?;
+    Ok(())
+}
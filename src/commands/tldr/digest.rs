@@ -0,0 +1,340 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use sea_orm::{
+    sea_query::OnConflict, ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter,
+    QueryOrder,
+};
+use serenity::{
+    all::{ChannelType, CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType},
+    builder::{CreateCommandOption, CreateEmbed, CreateMessage},
+    client::Context,
+    model::{id::ChannelId, Colour},
+    prelude::Mentionable,
+};
+use tokio::time::sleep;
+
+use entity::{prelude::TldrDigest, tldr_digest};
+
+use crate::{
+    commands::{
+        send_ephemeral_message,
+        tldr::{cache, generate, limiter, limits::Limits, privacy, summarize::Response, usage, Request},
+    },
+    quote::truncate,
+    util::DatabaseTypeMapKey,
+};
+
+// How often we check for digests that are due
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+// How far back a digest looks
+const WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+// Discord doesn't allow longer embed descriptions, or longer messages for the list
+const MAX_DIGEST_LENGTH: usize = 4096;
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+// The ready event fires on every reconnect, but we only want to post digests from one loop
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+pub(super) fn digest_options() -> Vec<CreateCommandOption> {
+    let channel_types = vec![ChannelType::Text, ChannelType::News];
+    vec![
+        CreateCommandOption::new(CommandOptionType::Channel, "channel", "The channel to summarize")
+            .required(true)
+            .channel_types(channel_types.clone()),
+        CreateCommandOption::new(CommandOptionType::Channel, "post_in", "Where to post the digest")
+            .required(true)
+            .channel_types(channel_types),
+        CreateCommandOption::new(CommandOptionType::String, "time", "When to post it every day, as HH:MM")
+            .required(true),
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "timezone",
+            "The timezone of that time, like Europe/Amsterdam",
+        ),
+        CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "min_messages",
+            "Skip the day if the channel saw fewer messages than this",
+        )
+        .min_int_value(1),
+    ]
+}
+
+pub(super) async fn handle_digest(ctx: Context, cmd: CommandInteraction, options: &[CommandDataOption]) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+    if !may_manage(&cmd)? {
+        return send_ephemeral_message(ctx, cmd, "You need the Manage Server permission to change this.").await;
+    }
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let limits = Limits::load(&db, guild_id).await?;
+
+    let mut channel = None;
+    let mut post_in = None;
+    let mut time = None;
+    let mut timezone = Tz::UTC;
+    let mut min_messages = limits.min_messages as i32;
+    for option in options {
+        match (option.name.as_str(), &option.value) {
+            ("channel", CommandDataOptionValue::Channel(value)) => channel = Some(*value),
+            ("post_in", CommandDataOptionValue::Channel(value)) => post_in = Some(*value),
+            ("time", CommandDataOptionValue::String(value)) => {
+                let Ok(value) = NaiveTime::parse_from_str(value.trim(), "%H:%M") else {
+                    return send_ephemeral_message(ctx, cmd, "Could not parse the time, use HH:MM.").await;
+                };
+                time = Some(value);
+            }
+            ("timezone", CommandDataOptionValue::String(value)) => {
+                let Ok(value) = value.trim().parse() else {
+                    return send_ephemeral_message(ctx, cmd, "Unknown timezone, use something like Europe/Amsterdam.")
+                        .await;
+                };
+                timezone = value;
+            }
+            ("min_messages", CommandDataOptionValue::Integer(value)) => min_messages = *value as i32,
+            _ => {}
+        }
+    }
+    let (Some(channel), Some(post_in), Some(time)) = (channel, post_in, time) else {
+        return send_ephemeral_message(ctx, cmd, "Pass a channel, where to post in and a time.").await;
+    };
+
+    let next_run = next_run(time, timezone, Utc::now());
+    let digest = tldr_digest::ActiveModel {
+        channel_id: Set(channel.get() as i64),
+        server_id: Set(guild_id.get() as i64),
+        digest_channel_id: Set(post_in.get() as i64),
+        min_messages: Set(min_messages),
+        time: Set(time),
+        timezone: Set(timezone.name().to_string()),
+        next_run: Set(next_run.fixed_offset()),
+    };
+    TldrDigest::insert(digest)
+        .on_conflict(
+            OnConflict::column(tldr_digest::Column::ChannelId)
+                .update_columns([
+                    tldr_digest::Column::DigestChannelId,
+                    tldr_digest::Column::MinMessages,
+                    tldr_digest::Column::Time,
+                    tldr_digest::Column::Timezone,
+                    tldr_digest::Column::NextRun,
+                ])
+                .to_owned(),
+        )
+        .exec_without_returning(&db)
+        .await?;
+    info!("{} set up a daily digest of channel {channel} in guild {guild_id}", cmd.user.name);
+
+    let content = format!(
+        "Every day at {} ({}) I'll post a digest of {} in {}, unless it saw fewer than {min_messages} messages. The first one comes <t:{}:R>.",
+        time.format("%H:%M"),
+        timezone.name(),
+        channel.mention(),
+        post_in.mention(),
+        next_run.timestamp()
+    );
+    send_ephemeral_message(ctx, cmd, &content).await
+}
+
+pub(super) async fn handle_undigest(
+    ctx: Context,
+    cmd: CommandInteraction,
+    options: &[CommandDataOption],
+) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+    if !may_manage(&cmd)? {
+        return send_ephemeral_message(ctx, cmd, "You need the Manage Server permission to change this.").await;
+    }
+    let Some(channel) = options.iter().find_map(|option| match option.value {
+        CommandDataOptionValue::Channel(channel) if option.name == "channel" => Some(channel),
+        _ => None,
+    }) else {
+        return send_ephemeral_message(ctx, cmd, "No channel passed.").await;
+    };
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let deleted = TldrDigest::delete_many()
+        .filter(tldr_digest::Column::ChannelId.eq(channel.get()))
+        .filter(tldr_digest::Column::ServerId.eq(guild_id.get()))
+        .exec(&db)
+        .await?
+        .rows_affected;
+
+    if deleted == 0 {
+        return send_ephemeral_message(ctx, cmd, "That channel doesn't have a daily digest.").await;
+    }
+    info!("{} removed the daily digest of channel {channel} in guild {guild_id}", cmd.user.name);
+    send_ephemeral_message(ctx, cmd, &format!("Stopped the daily digest of {}.", channel.mention())).await
+}
+
+pub(super) async fn handle_list(ctx: Context, cmd: CommandInteraction) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let digests = TldrDigest::find()
+        .filter(tldr_digest::Column::ServerId.eq(guild_id.get()))
+        .order_by_asc(tldr_digest::Column::NextRun)
+        .all(&db)
+        .await?;
+    if digests.is_empty() {
+        return send_ephemeral_message(ctx, cmd, "There are no daily digests.").await;
+    }
+
+    let lines: Vec<String> = digests
+        .iter()
+        .map(|digest| {
+            format!(
+                "{} in {} at {} ({}), from {} messages, next <t:{}:R>",
+                ChannelId::new(digest.channel_id as u64).mention(),
+                ChannelId::new(digest.digest_channel_id as u64).mention(),
+                digest.time.format("%H:%M"),
+                digest.timezone,
+                digest.min_messages,
+                digest.next_run.timestamp()
+            )
+        })
+        .collect();
+    send_ephemeral_message(ctx, cmd, &truncate(&lines.join("\n"), MAX_MESSAGE_LENGTH)).await
+}
+
+fn may_manage(cmd: &CommandInteraction) -> Result<bool> {
+    match cmd.member.as_ref().and_then(|m| m.permissions) {
+        Some(permissions) => Ok(permissions.manage_guild()),
+        None => Err(anyhow!("Could not fetch member permissions")),
+    }
+}
+
+/// Posts digests once they're due. Only starts a loop on the first call.
+pub(crate) async fn run(ctx: Context) {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    loop {
+        if let Err(e) = run_due(&ctx).await {
+            error!("Could not post daily digests: {e}");
+        }
+        sleep(POLL_INTERVAL).await;
+    }
+}
+
+async fn run_due(ctx: &Context) -> Result<()> {
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let now = Utc::now();
+    let due = TldrDigest::find().filter(tldr_digest::Column::NextRun.lte(now)).all(&db).await?;
+
+    for digest in due {
+        match post(ctx, &digest).await {
+            // Another tldr is keeping the server busy, try again on the next round
+            Ok(false) => continue,
+            Ok(true) => {}
+            Err(e) => error!("Could not post the daily digest of channel {}: {e}", digest.channel_id),
+        }
+
+        let timezone = digest.timezone.parse().unwrap_or(Tz::UTC);
+        let next_run = next_run(digest.time, timezone, now);
+        let mut active = digest.into_active_model();
+        active.next_run = Set(next_run.fixed_offset());
+        active.update(&db).await?;
+    }
+    Ok(())
+}
+
+/// Summarizes the past day of a channel into its digest channel. Returns false if it has to wait for the rate limit.
+async fn post(ctx: &Context, digest: &tldr_digest::Model) -> Result<bool> {
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let Some(request) = Request::from_digest(ctx, digest, WINDOW).await? else {
+        info!("Skipping the daily digest of channel {}, as it isn't a guild channel", digest.channel_id);
+        return Ok(true);
+    };
+
+    if privacy::channel_excluded(&db, &request).await? {
+        info!("Skipping the daily digest of channel {}, as AI features are turned off there", digest.channel_id);
+        return Ok(true);
+    }
+    if let Some(budget) = request.budget {
+        if usage::month_total(&db, request.guild_id).await? >= budget {
+            info!("Skipping the daily digest of channel {}, as its server has used up its budget", digest.channel_id);
+            return Ok(true);
+        }
+    }
+    // The digest counts as a tldr requested by the bot itself
+    let bot = ctx.cache.current_user().id;
+    let Ok(permit) = limiter::acquire(request.guild_id, bot) else { return Ok(false) };
+
+    let messages = request.collect(ctx).await?;
+    if messages.len() < request.min {
+        info!("Skipping the daily digest of channel {}, as it only saw {} messages", digest.channel_id, messages.len());
+        return Ok(true);
+    }
+
+    privacy::audit(&db, &request, bot, "digest", &messages).await?;
+    let mut tokens_used = 0;
+    let summary = generate(ctx, &Response::Scheduled, &request, &messages, &mut tokens_used).await;
+
+    permit.spend(tokens_used);
+    usage::record(&db, request.guild_id, bot, tokens_used).await?;
+
+    // Keep it around like any other tldr, so it can be handed out again or continued from
+    let summary = summary?;
+    cache::store(&db, &request, &messages, &summary).await?;
+
+    let embed = CreateEmbed::new()
+        .title(format!("The past day in #{}", request.channel.name))
+        .colour(Colour::FABLED_PINK)
+        .description(truncate(&summary, MAX_DIGEST_LENGTH));
+    ChannelId::new(digest.digest_channel_id as u64).send_message(ctx, CreateMessage::new().embed(embed)).await?;
+    Ok(true)
+}
+
+/// The first time after the given moment that the digest is due, at the same local time every day.
+fn next_run(time: NaiveTime, timezone: Tz, after: DateTime<Utc>) -> DateTime<Utc> {
+    let today = after.with_timezone(&timezone).date_naive();
+    today
+        .iter_days()
+        .take(3)
+        // That time might not exist on the day of a DST switch
+        .filter_map(|date| timezone.from_local_datetime(&date.and_time(time)).earliest())
+        .map(|at| at.to_utc())
+        .find(|at| *at > after)
+        .unwrap_or(after + WINDOW)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, NaiveTime, Utc};
+    use chrono_tz::Europe::Amsterdam;
+
+    use super::next_run;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn runs_later_today() {
+        assert_eq!(next_run(time(9, 0), Amsterdam, at("2026-06-01T05:00:00Z")), at("2026-06-01T07:00:00Z"));
+    }
+
+    #[test]
+    fn runs_tomorrow_once_the_time_passed() {
+        assert_eq!(next_run(time(9, 0), Amsterdam, at("2026-06-01T07:00:00Z")), at("2026-06-02T07:00:00Z"));
+        assert_eq!(next_run(time(9, 0), Amsterdam, at("2026-06-01T08:00:00Z")), at("2026-06-02T07:00:00Z"));
+    }
+
+    #[test]
+    fn skips_a_time_that_doesnt_exist() {
+        // The clocks skip from 2:00 to 3:00 that night
+        assert_eq!(next_run(time(2, 30), Amsterdam, at("2026-03-28T12:00:00Z")), at("2026-03-30T00:30:00Z"));
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Mutex, OnceLock},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use serenity::model::id::{GuildId, UserId};
+
+// How many tokens a guild, and a single member within it, may spend per minute. That is also as much as they can save up.
+const GUILD_TOKENS_PER_MINUTE: f64 = 10000.0;
+const USER_TOKENS_PER_MINUTE: f64 = 5000.0;
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+enum Key {
+    Guild(GuildId),
+    User(GuildId, UserId),
+}
+
+impl Key {
+    fn tokens_per_minute(self) -> f64 {
+        match self {
+            Key::Guild(_) => GUILD_TOKENS_PER_MINUTE,
+            Key::User(..) => USER_TOKENS_PER_MINUTE,
+        }
+    }
+}
+
+/// A token bucket: it fills up over time, and a tldr can run as long as it isn't empty.
+/// What a tldr ends up spending is taken out afterwards, which can leave it below zero for a while.
+struct Bucket {
+    tokens: f64,
+    updated: DateTime<Utc>,
+}
+
+impl Bucket {
+    fn refill(&mut self, key: Key, now: DateTime<Utc>) {
+        let minutes = (now - self.updated).num_milliseconds() as f64 / 60000.0;
+        self.tokens = (self.tokens + minutes * key.tokens_per_minute()).min(key.tokens_per_minute());
+        self.updated = now;
+    }
+
+    /// When the bucket is no longer empty.
+    fn available_at(&self, key: Key) -> DateTime<Utc> {
+        if self.tokens > 0.0 {
+            return self.updated;
+        }
+        let minutes = -self.tokens / key.tokens_per_minute();
+        self.updated + Duration::milliseconds((minutes * 60000.0).ceil() as i64 + 1)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    buckets: HashMap<Key, Bucket>,
+    // Guilds that have a tldr in progress, we only do one at a time per guild
+    running: HashSet<GuildId>,
+}
+
+// This is only ever locked briefly, and never across an await
+static STATE: OnceLock<Mutex<State>> = OnceLock::new();
+
+fn state() -> std::sync::MutexGuard<'static, State> {
+    STATE.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub(super) enum Denied {
+    Running,
+    Until(DateTime<Utc>),
+}
+
+/// Allows a tldr to run until dropped, spend what it used through it.
+pub(super) struct Permit {
+    guild_id: GuildId,
+    user_id: UserId,
+}
+
+pub(super) fn acquire(guild_id: GuildId, user_id: UserId) -> Result<Permit, Denied> {
+    let mut state = state();
+    if state.running.contains(&guild_id) {
+        return Err(Denied::Running);
+    }
+
+    let now = Utc::now();
+    // A full bucket is no different from one we never made, so those don't need to be kept around
+    state.buckets.retain(|key, bucket| {
+        bucket.refill(*key, now);
+        bucket.tokens < key.tokens_per_minute()
+    });
+
+    let mut available_at = now;
+    for key in [Key::Guild(guild_id), Key::User(guild_id, user_id)] {
+        let bucket =
+            state.buckets.entry(key).or_insert_with(|| Bucket { tokens: key.tokens_per_minute(), updated: now });
+        bucket.refill(key, now);
+        available_at = available_at.max(bucket.available_at(key));
+    }
+    if available_at > now {
+        return Err(Denied::Until(available_at));
+    }
+
+    state.running.insert(guild_id);
+    Ok(Permit { guild_id, user_id })
+}
+
+impl Permit {
+    pub(super) fn spend(&self, tokens: u32) {
+        let mut state = state();
+        let now = Utc::now();
+        for key in [Key::Guild(self.guild_id), Key::User(self.guild_id, self.user_id)] {
+            // Our buckets may have been let go while we ran, if they were full
+            let bucket =
+                state.buckets.entry(key).or_insert_with(|| Bucket { tokens: key.tokens_per_minute(), updated: now });
+            bucket.refill(key, now);
+            bucket.tokens -= tokens as f64;
+        }
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        state().running.remove(&self.guild_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use serenity::model::id::{GuildId, UserId};
+
+    use super::{acquire, state, Bucket, Denied, Key, GUILD_TOKENS_PER_MINUTE};
+
+    #[test]
+    fn refills_up_to_a_minute_of_tokens() {
+        let key = Key::Guild(GuildId::new(1));
+        let now = Utc::now();
+        let mut bucket = Bucket { tokens: 0.0, updated: now };
+
+        bucket.refill(key, now + Duration::seconds(30));
+        assert!((bucket.tokens - GUILD_TOKENS_PER_MINUTE / 2.0).abs() < 1.0);
+        bucket.refill(key, now + Duration::minutes(10));
+        assert_eq!(bucket.tokens, GUILD_TOKENS_PER_MINUTE);
+    }
+
+    #[test]
+    fn is_available_again_once_refilled_past_zero() {
+        let key = Key::Guild(GuildId::new(1));
+        let now = Utc::now();
+        assert_eq!(Bucket { tokens: 1.0, updated: now }.available_at(key), now);
+
+        let available_at = Bucket { tokens: -GUILD_TOKENS_PER_MINUTE, updated: now }.available_at(key);
+        assert!(available_at > now + Duration::seconds(59) && available_at <= now + Duration::seconds(61));
+    }
+
+    #[test]
+    fn runs_one_tldr_per_guild_at_a_time() {
+        // Every test works with its own guilds, as the state is shared
+        let (guild_id, user_id) = (GuildId::new(1001), UserId::new(1));
+        let permit = acquire(guild_id, user_id).ok().unwrap();
+        assert!(matches!(acquire(guild_id, UserId::new(2)), Err(Denied::Running)));
+        assert!(acquire(GuildId::new(1002), user_id).is_ok());
+
+        drop(permit);
+        assert!(acquire(guild_id, user_id).is_ok());
+    }
+
+    #[test]
+    fn holds_off_until_what_was_spent_is_refilled() {
+        let (guild_id, user_id) = (GuildId::new(1003), UserId::new(1));
+        let permit = acquire(guild_id, user_id).ok().unwrap();
+        permit.spend(GUILD_TOKENS_PER_MINUTE as u32 * 2);
+        drop(permit);
+
+        let Err(Denied::Until(until)) = acquire(guild_id, user_id) else { panic!("Expected the guild to be limited") };
+        assert!(until > Utc::now() + Duration::seconds(30));
+    }
+
+    #[test]
+    fn forgets_full_buckets_but_still_spends_from_them() {
+        let (guild_id, user_id) = (GuildId::new(1005), UserId::new(1));
+        let permit = acquire(guild_id, user_id).ok().unwrap();
+        // Nothing was spent yet, so another guild's tldr lets go of our buckets
+        assert!(acquire(GuildId::new(1006), user_id).is_ok());
+        assert!(!state().buckets.contains_key(&Key::Guild(guild_id)));
+
+        permit.spend(GUILD_TOKENS_PER_MINUTE as u32 * 2);
+        drop(permit);
+        assert!(matches!(acquire(guild_id, user_id), Err(Denied::Until(_))));
+    }
+}
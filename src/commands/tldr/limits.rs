@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+use serenity::{
+    all::{CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType},
+    builder::CreateCommandOption,
+    client::Context,
+    model::id::GuildId,
+};
+
+use crate::{
+    commands::send_ephemeral_message,
+    util::{format_duration, kvstore, parse_duration, DatabaseTypeMapKey},
+};
+
+// The bounds within which a guild can configure its limits
+const MAX_WINDOW: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+const MAX_MESSAGES: u32 = 2000;
+
+/// How far back, and over how many messages, a tldr may go in a guild.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub(super) struct Limits {
+    // In seconds
+    default_window: u64,
+    max_window: u64,
+    pub(super) min_messages: u32,
+    pub(super) max_messages: u32,
+    // How many tokens the guild may spend on tldrs per month, if limited
+    pub(super) monthly_budget: Option<u64>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            default_window: 16 * 60 * 60,
+            max_window: 24 * 60 * 60,
+            min_messages: 50,
+            max_messages: 500,
+            monthly_budget: None,
+        }
+    }
+}
+
+fn key(guild_id: GuildId) -> String {
+    format!("tldr_limits_{guild_id}")
+}
+
+/// Removes the limits of a guild, for when the bot is removed from it.
+pub(crate) async fn forget(db: &DatabaseConnection, guild_id: GuildId) -> Result<()> {
+    kvstore::delete(db, &key(guild_id)).await
+}
+
+impl Limits {
+    pub(super) async fn load(db: &DatabaseConnection, guild_id: GuildId) -> Result<Self> {
+        Ok(kvstore::get(db, &key(guild_id)).await?.unwrap_or_default())
+    }
+
+    pub(super) fn default_window(&self) -> Duration {
+        Duration::from_secs(self.default_window)
+    }
+
+    pub(super) fn max_window(&self) -> Duration {
+        Duration::from_secs(self.max_window)
+    }
+
+    fn describe(&self) -> String {
+        let budget = match self.monthly_budget {
+            Some(budget) => format!("This server can spend {budget} tokens per month."),
+            None => "There is no monthly budget.".to_string(),
+        };
+        format!(
+            "By default a tldr looks back {}, and at most {}. It needs at least {} messages, and reads up to {}. {budget}",
+            format_duration(self.default_window()),
+            format_duration(self.max_window()),
+            self.min_messages,
+            self.max_messages
+        )
+    }
+}
+
+pub(super) fn limit_options() -> Vec<CreateCommandOption> {
+    vec![
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "default_window",
+            "How far back a tldr looks if not told otherwise, like 16h",
+        ),
+        CreateCommandOption::new(CommandOptionType::String, "max_window", "How far back a tldr can look at most"),
+        CreateCommandOption::new(CommandOptionType::Integer, "min_messages", "How many messages a tldr needs at least")
+            .min_int_value(1)
+            .max_int_value(MAX_MESSAGES as u64),
+        CreateCommandOption::new(CommandOptionType::Integer, "max_messages", "How many messages a tldr reads at most")
+            .min_int_value(1)
+            .max_int_value(MAX_MESSAGES as u64),
+        CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "monthly_budget",
+            "How many tokens tldrs may use per month, 0 for no limit",
+        )
+        .min_int_value(0),
+    ]
+}
+
+pub(super) async fn handle_limits(ctx: Context, cmd: CommandInteraction, options: &[CommandDataOption]) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+
+    let permissions = match cmd.member.as_ref().and_then(|m| m.permissions) {
+        Some(p) => p,
+        None => return Err(anyhow!("Could not fetch member permissions")),
+    };
+    if !permissions.manage_guild() {
+        return send_ephemeral_message(ctx, cmd, "You need the Manage Server permission to change this.").await;
+    }
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let mut limits = Limits::load(&db, guild_id).await?;
+
+    for option in options {
+        match (option.name.as_str(), &option.value) {
+            ("default_window" | "max_window", CommandDataOptionValue::String(value)) => {
+                let Some(window) = parse_duration(value).filter(|window| *window <= MAX_WINDOW) else {
+                    let error = format!(
+                        "Could not use {value}, try something like 2h or 30m (up to {}).",
+                        format_duration(MAX_WINDOW)
+                    );
+                    return send_ephemeral_message(ctx, cmd, &error).await;
+                };
+                match option.name.as_str() {
+                    "default_window" => limits.default_window = window.as_secs(),
+                    _ => limits.max_window = window.as_secs(),
+                }
+            }
+            ("min_messages", CommandDataOptionValue::Integer(value)) => limits.min_messages = *value as u32,
+            ("max_messages", CommandDataOptionValue::Integer(value)) => limits.max_messages = *value as u32,
+            ("monthly_budget", CommandDataOptionValue::Integer(value)) => {
+                limits.monthly_budget = (*value > 0).then_some(*value as u64)
+            }
+            _ => {}
+        }
+    }
+
+    if limits.default_window > limits.max_window {
+        return send_ephemeral_message(ctx, cmd, "The default window can't be longer than the maximum window.").await;
+    }
+    if limits.min_messages > limits.max_messages {
+        return send_ephemeral_message(ctx, cmd, "The minimum amount of messages can't be more than the maximum.")
+            .await;
+    }
+
+    kvstore::set(&db, &key(guild_id), &limits).await?;
+    send_ephemeral_message(ctx, cmd, &limits.describe()).await
+}
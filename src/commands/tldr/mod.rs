@@ -0,0 +1,860 @@
+use std::{
+    cmp::min,
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use serenity::{
+    all::{
+        ChannelId, ChannelType, Command, CommandDataOption, CommandDataOptionValue, CommandInteraction,
+        CommandOptionType, CreateInteractionResponseMessage, GuildChannel, GuildId, Member, Message, MessageId, User,
+        UserId,
+    },
+    builder::{CreateCommand, CreateCommandOption, CreateInteractionResponse},
+    client::Context,
+    futures::StreamExt,
+    prelude::Mentionable,
+    utils::parse_message_url,
+};
+use tiktoken_rs::CoreBPE;
+
+use entity::{archived_message, prelude::ArchivedMessage, tldr_digest, tldr_summary};
+
+use crate::{
+    archive,
+    commands::{
+        edit_interaction, send_ephemeral_message,
+        tldr::{
+            limiter::{Denied, Permit},
+            limits::Limits,
+            prompts::Prompts,
+            summarize::{summarize, Response},
+        },
+    },
+    llm::{ChatMessage, Role, IMAGE_TOKENS},
+    util::{format_duration, parse_duration, DatabaseTypeMapKey, TLDRTypeMapKey},
+};
+
+pub(super) mod ask;
+pub(super) mod cache;
+pub(super) mod digest;
+mod limiter;
+pub(crate) mod limits;
+mod privacy;
+pub(crate) mod prompts;
+mod summarize;
+mod usage;
+
+// How many images are sent along with a single tldr at most
+const MAX_IMAGES: usize = 10;
+
+pub(super) async fn register(ctx: &Context) -> Result<()> {
+    Command::create_global_command(
+        ctx,
+        CreateCommand::new("tldr")
+            .description("Posts a tl;dr of the recent messages")
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "summarize", "Summarizes the recent messages")
+                    .add_sub_option(since_option())
+                    .add_sub_option(
+                        CreateCommandOption::new(
+                            CommandOptionType::Integer,
+                            "min",
+                            "Don't bother below this many messages",
+                        )
+                        .min_int_value(1),
+                    )
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::Integer, "max", "Read at most this many messages")
+                            .min_int_value(1),
+                    )
+                    .add_sub_option(CreateCommandOption::new(
+                        CommandOptionType::User,
+                        "user",
+                        "Only summarize what this member said",
+                    ))
+                    .add_sub_option(thread_option("Summarize this thread or forum post instead"))
+                    .add_sub_option(replies_option(
+                        "Summarize the replies to this message instead, given a message link",
+                    )),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "limits",
+                    "Changes how far back and over how many messages a tldr may go",
+                )
+                .set_sub_options(limits::limit_options()),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "usage",
+                "Shows how many tokens this server spent on tldrs this month",
+            ))
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "digest",
+                    "Posts a summary of a channel's day every day",
+                )
+                .set_sub_options(digest::digest_options()),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "undigest",
+                    "Stops the daily digest of a channel",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Channel, "channel", "The channel being digested")
+                        .required(true),
+                ),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "digests",
+                "Lists the daily digests of this server",
+            ))
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "prompt", "Changes how tldrs are written")
+                    .set_sub_options(prompts::prompt_options()),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "opt_out",
+                    "Keeps your messages from being sent to the language model",
+                )
+                .set_sub_options(privacy::opt_out_options()),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "channel_opt_out",
+                    "Turns AI features off or on for a channel",
+                )
+                .set_sub_options(privacy::channel_opt_out_options()),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "audit",
+                "Shows what was recently sent to the language model",
+            ))
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "preview",
+                    "Shows what the bot would be told for a tldr here",
+                )
+                .add_sub_option(thread_option("Preview for this thread or forum post instead")),
+            ),
+    )
+    .await?;
+    Ok(())
+}
+
+fn since_option() -> CreateCommandOption {
+    CreateCommandOption::new(
+        CommandOptionType::String,
+        "since",
+        "How far back to look: a duration like 2h, \"me\" for your last message, \"tldr\", or a message link",
+    )
+}
+
+fn replies_option(description: &str) -> CreateCommandOption {
+    CreateCommandOption::new(CommandOptionType::String, "replies", description)
+}
+
+fn thread_option(description: &str) -> CreateCommandOption {
+    CreateCommandOption::new(CommandOptionType::Channel, "thread", description).channel_types(vec![
+        ChannelType::PublicThread,
+        ChannelType::PrivateThread,
+        ChannelType::NewsThread,
+    ])
+}
+
+pub(super) async fn handle_command(ctx: Context, cmd: CommandInteraction) -> Result<()> {
+    let Some((subcommand, options)) = cmd.data.options.first().and_then(|option| match &option.value {
+        CommandDataOptionValue::SubCommand(options) => Some((option.name.clone(), options.clone())),
+        _ => None,
+    }) else {
+        return send_ephemeral_message(ctx, cmd, "No subcommand passed").await;
+    };
+
+    match subcommand.as_str() {
+        "summarize" => handle_summarize(ctx, cmd, &options).await,
+        "limits" => limits::handle_limits(ctx, cmd, &options).await,
+        "usage" => usage::handle_usage(ctx, cmd).await,
+        "digest" => digest::handle_digest(ctx, cmd, &options).await,
+        "undigest" => digest::handle_undigest(ctx, cmd, &options).await,
+        "digests" => digest::handle_list(ctx, cmd).await,
+        "prompt" => prompts::handle_prompt(ctx, cmd, &options).await,
+        "preview" => prompts::handle_preview(ctx, cmd, &options).await,
+        "opt_out" => privacy::handle_opt_out(ctx, cmd, &options).await,
+        "channel_opt_out" => privacy::handle_channel_opt_out(ctx, cmd, &options).await,
+        "audit" => privacy::handle_audit(ctx, cmd).await,
+        _ => send_ephemeral_message(ctx, cmd, "Unknown subcommand").await,
+    }
+}
+
+/// Where a tldr starts reading from.
+enum Since {
+    Window(Duration),
+    // The last message of the one asking for the tldr
+    LastMessageOf(UserId),
+    Message(MessageId),
+    // Everything after this message, which the previous tldr ended at
+    After(MessageId),
+}
+
+/// The messages a tldr should be about, as asked for by the user and bounded by the guild's limits.
+struct Request {
+    guild_id: GuildId,
+    channel: GuildChannel,
+    // The channel a thread or forum post is in
+    parent: Option<GuildChannel>,
+    since: Since,
+    // Only read the reply chain that starts at this message
+    replies_to: Option<MessageId>,
+    // We never read messages older than this, whatever the user asked for
+    earliest: DateTime<Utc>,
+    min: usize,
+    max: usize,
+    user: Option<UserId>,
+    // The summary this one continues from
+    previous: Option<String>,
+    // How many tokens the guild may spend per month
+    budget: Option<u64>,
+}
+
+impl Request {
+    /// Reads the options of the command, or returns why they can't be used.
+    async fn parse(
+        ctx: &Context,
+        cmd: &CommandInteraction,
+        options: &[CommandDataOption],
+    ) -> Result<Result<Self, String>> {
+        let Some(guild_id) = cmd.guild_id else { return Ok(Err("This command can only be used in servers.".into())) };
+        let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+        let limits = Limits::load(&db, guild_id).await?;
+
+        let mut thread = None;
+        let mut replies_to = None;
+        let mut since_given = false;
+        let mut continues = false;
+        let mut since = Since::Window(limits.default_window());
+        let mut min = limits.min_messages as usize;
+        let mut max = limits.max_messages as usize;
+        let mut user = None;
+        let mut linked_channel = None;
+        for option in options {
+            match (option.name.as_str(), &option.value) {
+                ("thread", CommandDataOptionValue::Channel(id)) => thread = Some(*id),
+                ("user", CommandDataOptionValue::User(id)) => user = Some(*id),
+                ("min", CommandDataOptionValue::Integer(value)) => min = *value as usize,
+                ("max", CommandDataOptionValue::Integer(value)) => max = *value as usize,
+                ("replies", CommandDataOptionValue::String(value)) => {
+                    let Some((_, message_channel, message_id)) = parse_message_url(value.trim()) else {
+                        return Ok(Err("Replies needs a message link, which you can copy from the message.".into()));
+                    };
+                    replies_to = Some((message_channel, message_id));
+                }
+                ("since", CommandDataOptionValue::String(value)) => {
+                    since_given = true;
+                    let value = value.trim();
+                    since = if ["me", "mine", "my last message"].contains(&value.to_lowercase().as_str()) {
+                        Since::LastMessageOf(cmd.user.id)
+                    } else if ["tldr", "last tldr", "summary"].contains(&value.to_lowercase().as_str()) {
+                        // Where the last tldr ended depends on the channel, which we don't know yet
+                        continues = true;
+                        Since::Window(limits.default_window())
+                    } else if let Some((_, message_channel, message_id)) = parse_message_url(value) {
+                        linked_channel = Some(message_channel);
+                        Since::Message(message_id)
+                    } else if let Some(window) = parse_duration(value) {
+                        if window > limits.max_window() {
+                            let error =
+                                format!("A tldr can look back {} at most.", format_duration(limits.max_window()));
+                            return Ok(Err(error));
+                        }
+                        Since::Window(window)
+                    } else {
+                        return Ok(Err(
+                            "Could not understand since, try something like 2h, \"me\", \"tldr\" or a message link."
+                                .into(),
+                        ));
+                    };
+                }
+                _ => {}
+            }
+        }
+
+        if max > limits.max_messages as usize {
+            return Ok(Err(format!("A tldr can read {} messages at most.", limits.max_messages)));
+        }
+        if min < limits.min_messages as usize || min > max {
+            let error = format!("The minimum amount of messages has to be between {} and {max}.", limits.min_messages);
+            return Ok(Err(error));
+        }
+
+        // A reply chain is read from its first message on, in whichever channel that was posted
+        if let Some((message_channel, message_id)) = replies_to {
+            if since_given {
+                return Ok(Err(
+                    "A reply chain always starts at its first message, so it can't be combined with since.".into(),
+                ));
+            }
+            since = Since::Message(message_id);
+            linked_channel = Some(message_channel);
+        }
+        let channel_id = thread.or(linked_channel).unwrap_or(cmd.channel_id);
+
+        let Some(channel) = channel_id.to_channel(ctx).await?.guild().filter(|c| c.guild_id == guild_id) else {
+            return Ok(Err("That is not a valid channel.".into()));
+        };
+        if linked_channel.is_some_and(|linked_channel| linked_channel != channel.id) {
+            return Ok(Err("That message link is from a different channel.".into()));
+        }
+        let parent = parent(ctx, &channel).await?;
+
+        let mut previous = None;
+        if continues {
+            let Some(last) = cache::latest(&db, channel.id, user).await? else {
+                return Ok(Err("There is no earlier tldr to continue from here.".into()));
+            };
+            since = Since::After(MessageId::new(last.last_message_id as u64));
+            previous = Some(last.text);
+        }
+
+        let earliest = Utc::now()
+            - match since {
+                Since::Window(window) => window,
+                _ => limits.max_window(),
+            };
+        Ok(Ok(Self {
+            guild_id,
+            channel,
+            parent,
+            since,
+            replies_to: replies_to.map(|(_, message_id)| message_id),
+            earliest,
+            min,
+            max,
+            user,
+            previous,
+            budget: limits.monthly_budget,
+        }))
+    }
+
+    /// Rebuilds the request a cached summary was made for, reading from where it started up until now.
+    async fn from_cached(ctx: &Context, cached: &tldr_summary::Model) -> Result<Option<Self>> {
+        let guild_id = GuildId::new(cached.server_id as u64);
+        let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+        let limits = Limits::load(&db, guild_id).await?;
+
+        let Some(channel) = ChannelId::new(cached.channel_id as u64).to_channel(ctx).await?.guild() else {
+            return Ok(None);
+        };
+        let parent = parent(ctx, &channel).await?;
+        Ok(Some(Self {
+            guild_id,
+            channel,
+            parent,
+            since: Since::Message(MessageId::new(cached.first_message_id as u64)),
+            replies_to: cached.replies_to.map(|id| MessageId::new(id as u64)),
+            earliest: Utc::now() - limits.max_window(),
+            min: 1,
+            max: limits.max_messages as usize,
+            user: cached.user_id.map(|id| UserId::new(id as u64)),
+            previous: None,
+            budget: limits.monthly_budget,
+        }))
+    }
+
+    /// The request for a digest, which reads everything a channel said over the past day.
+    async fn from_digest(ctx: &Context, digest: &tldr_digest::Model, window: Duration) -> Result<Option<Self>> {
+        let guild_id = GuildId::new(digest.server_id as u64);
+        let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+        let limits = Limits::load(&db, guild_id).await?;
+
+        let Some(channel) = ChannelId::new(digest.channel_id as u64).to_channel(ctx).await?.guild() else {
+            return Ok(None);
+        };
+        let parent = parent(ctx, &channel).await?;
+        Ok(Some(Self {
+            guild_id,
+            channel,
+            parent,
+            since: Since::Window(window),
+            replies_to: None,
+            earliest: Utc::now() - window,
+            min: digest.min_messages.max(1) as usize,
+            max: limits.max_messages as usize,
+            user: None,
+            previous: None,
+            budget: limits.monthly_budget,
+        }))
+    }
+
+    /// Makes sure the guild has budget left and nobody is going too fast, or returns why the request has to wait.
+    async fn acquire(&self, db: &DatabaseConnection, user_id: UserId) -> Result<Result<Permit, String>> {
+        if privacy::channel_excluded(db, self).await? {
+            return Ok(Err("AI features are turned off in this channel.".into()));
+        }
+        if let Some(budget) = self.budget {
+            if usage::month_total(db, self.guild_id).await? >= budget {
+                return Ok(Err("This server has used up its TLDR budget for this month.".into()));
+            }
+        }
+
+        // Only one request runs per server at a time, and the server and member need to have tokens left
+        Ok(match limiter::acquire(self.guild_id, user_id) {
+            Ok(permit) => Ok(permit),
+            Err(Denied::Running) => {
+                Err("Please wait a little, I'm already thinking about a TLDR in this server.".into())
+            }
+            Err(Denied::Until(at)) => Err(format!(
+                "Please wait a little, this command is being used too fast. Try again <t:{}:R>.",
+                at.timestamp()
+            )),
+        })
+    }
+
+    /// Reads the messages this request is about, oldest first.
+    async fn collect(&self, ctx: &Context) -> Result<Vec<HistoryMessage>> {
+        let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+        let mut messages = match self.collect_archived(&db).await? {
+            Some(messages) => messages,
+            None => self.collect_live(ctx).await?,
+        };
+
+        if let Some(root) = self.replies_to {
+            // Keep the messages that reply to the root, or to a message that does
+            let mut chain = HashSet::from([root]);
+            messages.retain(|message| {
+                let in_chain = message.id == root || message.reference.is_some_and(|id| chain.contains(&id));
+                if in_chain {
+                    chain.insert(message.id);
+                }
+                in_chain
+            });
+        } else if self.user.is_none() {
+            // Whatever started the thread is what it's about, even if it was posted a while ago
+            if let Some(starter) = self.thread_starter(ctx).await {
+                if !messages.iter().any(|message| message.id == starter.id) {
+                    messages.insert(0, starter);
+                }
+            }
+        }
+
+        // Leave out everyone that doesn't want their messages sent to the model, and their name in replies to them
+        let excluded = privacy::excluded_members(&db, self.guild_id).await?;
+        messages.retain(|message| !excluded.contains(&message.author_id));
+        for message in &mut messages {
+            match message.reply_to_id {
+                Some(id) if !excluded.contains(&id) => {}
+                Some(_) => message.reply_to = Some("someone".to_string()),
+                // Without knowing who was replied to, we can't tell whether they opted out
+                None => message.reply_to = None,
+            }
+        }
+        Ok(messages)
+    }
+
+    /// The message a thread was started from, or the opening post of a forum post.
+    async fn thread_starter(&self, ctx: &Context) -> Option<HistoryMessage> {
+        let parent = self.parent.as_ref()?;
+        // Starter messages share their id with the thread, forum posts keep theirs in the thread itself
+        let channel_id = match parent.kind {
+            ChannelType::Forum => self.channel.id,
+            _ => parent.id,
+        };
+        let mut message = channel_id.message(ctx, MessageId::new(self.channel.id.get())).await.ok()?;
+        message.guild_id = Some(self.guild_id);
+        Some(HistoryMessage::from_message(ctx, message).await)
+    }
+
+    /// Reads the messages from Discord, newest first until we've gone back far enough.
+    async fn collect_live(&self, ctx: &Context) -> Result<Vec<HistoryMessage>> {
+        let mut messages = Vec::new(); // A place to store all the history to send to the model
+
+        // Get a history of messages
+        let mut msg_iter = self.channel.id.messages_iter(ctx).boxed();
+        while let Some(message) = msg_iter.next().await {
+            let mut message = message?;
+
+            if message.timestamp < self.earliest.into() {
+                break;
+            }
+            match self.since {
+                Since::LastMessageOf(user) if message.author.id == user => break,
+                Since::Message(id) if message.id < id => break,
+                Since::After(id) if message.id <= id => break,
+                _ => {}
+            }
+
+            // Ignore messages from the bot
+            if message.author.bot {
+                continue;
+            }
+            // Ignore everyone else, if we're only interested in one member
+            if self.user.is_some_and(|user| message.author.id != user) {
+                continue;
+            }
+            // Make sure the guild is set
+            if message.guild_id.is_none() {
+                message.guild_id = Some(self.guild_id);
+            }
+
+            // Ignore messages we can't process
+            let message = HistoryMessage::from_message(ctx, message).await;
+            if message.content.is_empty() && message.extras.is_empty() {
+                continue;
+            }
+
+            messages.push(message);
+
+            if messages.len() >= self.max {
+                break;
+            }
+        }
+
+        // Sort it by timestamp, so it all makes sense
+        messages.sort_by_key(|m| m.timestamp);
+        Ok(messages)
+    }
+
+    /// Reads the messages from the archive rather than from Discord, if the channel is archived and far enough back.
+    async fn collect_archived(&self, db: &DatabaseConnection) -> Result<Option<Vec<HistoryMessage>>> {
+        let Some(archive) = archive::channel(db, self.channel.id).await? else { return Ok(None) };
+
+        let mut query = ArchivedMessage::find()
+            .filter(archived_message::Column::ChannelId.eq(self.channel.id.get()))
+            .filter(archived_message::Column::Timestamp.gte(self.earliest))
+            .filter(
+                Condition::any()
+                    .add(archived_message::Column::Content.ne(""))
+                    .add(archived_message::Column::Extras.is_not_null()),
+            );
+        let from = match self.since {
+            Since::Window(_) => self.earliest,
+            Since::Message(id) => {
+                query = query.filter(archived_message::Column::MessageId.gte(id.get()));
+                *id.created_at()
+            }
+            Since::After(id) => {
+                query = query.filter(archived_message::Column::MessageId.gt(id.get()));
+                *id.created_at()
+            }
+            Since::LastMessageOf(user) => {
+                let last = ArchivedMessage::find()
+                    .filter(archived_message::Column::ChannelId.eq(self.channel.id.get()))
+                    .filter(archived_message::Column::AuthorId.eq(user.get()))
+                    .filter(archived_message::Column::Timestamp.gte(self.earliest))
+                    .order_by_desc(archived_message::Column::Timestamp)
+                    .one(db)
+                    .await?;
+                match last {
+                    Some(last) => {
+                        query = query.filter(archived_message::Column::Timestamp.gt(last.timestamp));
+                        last.timestamp.to_utc()
+                    }
+                    None => self.earliest,
+                }
+            }
+        };
+        // The archive only goes back to when it was enabled, or as far as it was backfilled
+        if archive.covered_since.to_utc() > from {
+            return Ok(None);
+        }
+
+        if let Some(user) = self.user {
+            query = query.filter(archived_message::Column::AuthorId.eq(user.get()));
+        }
+        let messages = query.order_by_desc(archived_message::Column::Timestamp).limit(self.max as u64).all(db).await?;
+        let mut messages: Vec<HistoryMessage> = messages.into_iter().rev().map(HistoryMessage::from).collect();
+
+        // The archive only keeps the name of who was replied to, the message they replied to tells us who that is
+        let references: Vec<i64> =
+            messages.iter().filter_map(|message| message.reference).map(|id| id.get() as i64).collect();
+        let authors: HashMap<i64, i64> = ArchivedMessage::find()
+            .select_only()
+            .column(archived_message::Column::MessageId)
+            .column(archived_message::Column::AuthorId)
+            .filter(archived_message::Column::MessageId.is_in(references))
+            .into_tuple()
+            .all(db)
+            .await?
+            .into_iter()
+            .collect();
+        for message in &mut messages {
+            let author_id = message.reference.and_then(|id| authors.get(&(id.get() as i64)));
+            message.reply_to_id = author_id.map(|author_id| UserId::new(*author_id as u64));
+        }
+        Ok(Some(messages))
+    }
+
+    /// Tells the model where the conversation took place, so it knows what it's about.
+    fn context(&self) -> String {
+        let channel = &self.channel;
+        let mut context = match &self.parent {
+            Some(parent) => {
+                let kind = if parent.kind == ChannelType::Forum { "forum post" } else { "thread" };
+                let tags: Vec<String> = parent
+                    .available_tags
+                    .iter()
+                    .filter(|tag| channel.applied_tags.contains(&tag.id))
+                    .map(|tag| format!("\"{}\"", tag.name))
+                    .collect();
+                let tags = if tags.is_empty() { String::new() } else { format!(", tagged {}", tags.join(", ")) };
+                format!(
+                    "The conversation takes place in a {kind} called \"{}\" in the channel \"{}\"{tags}.",
+                    channel.name, parent.name
+                )
+            }
+            None => format!(
+                "The conversation takes place in a text channel called \"{}\"{}.",
+                channel.name,
+                channel.topic.as_ref().map(|t| format!(" with the assigned topic \"{t}\"")).unwrap_or_default()
+            ),
+        };
+        if self.replies_to.is_some() {
+            context.push_str(" The history is a chain of replies, starting at the message it all replies to.");
+        }
+        context
+    }
+
+    /// Describes which messages were read, for when there weren't enough of them.
+    fn describe(&self) -> String {
+        let since = match self.since {
+            Since::Window(window) => format!("in the last {}", format_duration(window)),
+            Since::LastMessageOf(_) => "since your last message".to_string(),
+            Since::Message(_) if self.replies_to.is_some() => "in that reply chain".to_string(),
+            Since::Message(_) => "since that message".to_string(),
+            Since::After(_) => "since the last tldr".to_string(),
+        };
+        match self.user {
+            Some(user) => format!("from {} {since}", user.mention()),
+            None => since,
+        }
+    }
+}
+
+async fn handle_summarize(ctx: Context, cmd: CommandInteraction, options: &[CommandDataOption]) -> Result<()> {
+    let request = match Request::parse(&ctx, &cmd, options).await? {
+        Ok(request) => request,
+        Err(e) => return send_ephemeral_message(ctx, cmd, &e).await,
+    };
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let permit = match request.acquire(&db, cmd.user.id).await? {
+        Ok(permit) => permit,
+        Err(e) => return send_ephemeral_message(ctx, cmd, &e).await,
+    };
+
+    // Tell the user the bot is thinking, as language models are not super fast.
+    cmd.create_response(&ctx, CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new())).await?;
+
+    let messages = request.collect(&ctx).await?;
+    if messages.len() < request.min {
+        let content = format!(
+            "Look, we're talking about {} messages {}, surely you can just scroll up.",
+            messages.len(),
+            request.describe()
+        );
+        return edit_interaction(ctx, cmd, &content).await;
+    }
+
+    // If hardly anything changed since the last time, that summary will do just fine
+    if let Some(cached) = cache::find(&db, &request, &messages).await? {
+        cmd.edit_response(&ctx, cache::response(&cached, true)).await?;
+        return Ok(());
+    }
+
+    privacy::audit(&db, &request, cmd.user.id, "tldr", &messages).await?;
+    let mut tokens_used = 0;
+    let summary = generate(&ctx, &Response::Command(&ctx, &cmd), &request, &messages, &mut tokens_used).await;
+
+    // Take what we actually used out of the buckets, and keep track of it for the budget, even if we failed halfway
+    permit.spend(tokens_used);
+    usage::record(&db, request.guild_id, cmd.user.id, tokens_used).await?;
+
+    let cached = cache::store(&db, &request, &messages, &summary?).await?;
+    cmd.edit_response(&ctx, cache::response(&cached, false)).await?;
+    Ok(())
+}
+
+/// Has the model summarize the messages, in as many parts as it takes.
+async fn generate(
+    ctx: &Context,
+    response: &Response<'_>,
+    request: &Request,
+    messages: &[HistoryMessage],
+    tokens_used: &mut u32,
+) -> Result<String> {
+    let (llm, bpe) = ctx.data.read().await.get::<TLDRTypeMapKey>().unwrap().clone();
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let prompts = Prompts::load(&db, request.guild_id).await?;
+
+    // Direct the model with an initial prompt, and decide how much detail we want
+    let variables = prompts::variables(ctx, request, bullets(messages.len()), &prompts);
+    let directive = ChatMessage::new(Role::System, prompts.directive(&variables));
+    let prompt = prompts.instructions(&variables, request.previous.is_some());
+
+    // Convert it all into messages for the model, after the summary we're continuing from
+    let mut history = Vec::with_capacity(messages.len() + 1);
+    if let Some(previous) = &request.previous {
+        history.push(ChatMessage::new(Role::System, format!("The previous summary of this conversation:\n{previous}")));
+    }
+    history.extend(messages.iter().map(|message| message.to_chat_message(llm.vision)));
+    limit_images(&mut history);
+
+    // Send it all off, prompting the model to write a summary, in as many parts as it takes
+    let prompt = ChatMessage::new(Role::User, prompt);
+    summarize(response, &llm, &bpe, directive, history, prompt, tokens_used).await
+}
+
+/// How many points a summary of this many messages should have at most.
+fn bullets(messages: usize) -> usize {
+    min(5 + (messages / 100), 10)
+}
+
+/// Looks up the channel a thread or forum post is in.
+async fn parent(ctx: &Context, channel: &GuildChannel) -> Result<Option<GuildChannel>> {
+    Ok(match (&channel.thread_metadata, channel.parent_id) {
+        (Some(_), Some(parent_id)) => parent_id.to_channel(ctx).await?.guild(),
+        _ => None,
+    })
+}
+
+/// A message from the history, as read from either Discord or the archive.
+struct HistoryMessage {
+    id: MessageId,
+    channel_id: ChannelId,
+    guild_id: Option<GuildId>,
+    timestamp: DateTime<Utc>,
+    author_id: UserId,
+    author: String,
+    // The message this one replies to, and who posted it
+    reference: Option<MessageId>,
+    reply_to: Option<String>,
+    reply_to_id: Option<UserId>,
+    content: String,
+    // Attachments, embeds and the like, described in words
+    extras: Vec<String>,
+    // Links to the attached images, the archive doesn't have these as they expire
+    images: Vec<String>,
+}
+
+impl HistoryMessage {
+    async fn from_message(ctx: &Context, msg: Message) -> Self {
+        let reply_to = match msg.referenced_message.as_ref() {
+            Some(reference) => {
+                Some(resolve_name(&reference.author, reference.member(ctx).await.ok().as_ref()).to_string())
+            }
+            None => None,
+        };
+
+        Self {
+            id: msg.id,
+            channel_id: msg.channel_id,
+            guild_id: msg.guild_id,
+            timestamp: *msg.timestamp,
+            author_id: msg.author.id,
+            author: resolve_name(&msg.author, msg.member(ctx).await.ok().as_ref()).to_string(),
+            reference: msg.message_reference.as_ref().and_then(|reference| reference.message_id),
+            reply_to,
+            reply_to_id: msg.referenced_message.as_ref().map(|reference| reference.author.id),
+            content: msg.content_safe(ctx),
+            extras: archive::extras(&msg),
+            images: msg
+                .attachments
+                .iter()
+                .filter(|attachment| attachment.content_type.as_ref().is_some_and(|t| t.starts_with("image/")))
+                .map(|attachment| attachment.url.clone())
+                .collect(),
+        }
+    }
+
+    fn link(&self) -> String {
+        self.id.link(self.channel_id, self.guild_id)
+    }
+
+    /// Describes the message to the model, with its images if the model can look at them.
+    fn to_chat_message(&self, vision: bool) -> ChatMessage {
+        let context = match &self.reply_to {
+            Some(reply_to) => format!(", in reply to {reply_to}"),
+            None => "".to_string(),
+        };
+        let time = self.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true);
+
+        let text = privacy::redact(&self.content);
+        let extras = privacy::redact(&self.extras.join(", "));
+        let content = if text.is_empty() {
+            format!("At {time}, {author}{context} {extras}", author = self.author)
+        } else if extras.is_empty() {
+            format!("At {time}, {author} says{context}: \"{text}\"", author = self.author)
+        } else {
+            format!("At {time}, {author} says{context}: \"{text}\" and {extras}", author = self.author)
+        };
+
+        let mut message = ChatMessage::new(Role::System, content);
+        if vision {
+            message.images = self.images.clone();
+        }
+        message
+    }
+}
+
+impl From<archived_message::Model> for HistoryMessage {
+    fn from(model: archived_message::Model) -> Self {
+        Self {
+            id: MessageId::new(model.message_id as u64),
+            channel_id: ChannelId::new(model.channel_id as u64),
+            guild_id: Some(GuildId::new(model.server_id as u64)),
+            timestamp: model.timestamp.to_utc(),
+            author_id: UserId::new(model.author_id as u64),
+            author: model.author,
+            reference: model.reply_to.map(|id| MessageId::new(id as u64)),
+            reply_to: model.reply_to_author,
+            // Filled in once we know the messages, from the one that was replied to
+            reply_to_id: None,
+            content: model.content,
+            extras: model.extras.and_then(|extras| serde_json::from_value(extras).ok()).unwrap_or_default(),
+            images: Vec::new(),
+        }
+    }
+}
+
+fn resolve_name<'a>(user: &'a User, member: Option<&'a Member>) -> &'a str {
+    member.map_or_else(
+        || user.global_name.as_deref().unwrap_or(user.name.as_str()),
+        |m| m.display_name(),
+    )
+}
+
+/// Only sends along the most recent images, as every one of them takes up a good chunk of the context.
+fn limit_images(history: &mut [ChatMessage]) {
+    let mut remaining = MAX_IMAGES;
+    for message in history.iter_mut().rev() {
+        message.images.truncate(remaining);
+        remaining -= message.images.len();
+    }
+}
+
+pub(super) fn num_tokens_from_messages(bpe: &CoreBPE, messages: &[ChatMessage]) -> Result<u32> {
+    let mut num_tokens: u32 = 0;
+    for message in messages {
+        num_tokens += 4; // every message follows <im_start>{role/name}\n{content}<im_end>\n;
+        num_tokens += bpe.encode_with_special_tokens("system").len() as u32;
+        num_tokens += bpe.encode_with_special_tokens(&message.content).len() as u32;
+        num_tokens += message.images.len() as u32 * IMAGE_TOKENS;
+    }
+    num_tokens += 3; // every reply is primed with <|start|>assistant<|message|>
+    Ok(num_tokens)
+}
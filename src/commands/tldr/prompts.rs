@@ -0,0 +1,330 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+use serenity::{
+    all::{CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType},
+    builder::{CreateCommandOption, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage},
+    client::Context,
+    model::{id::GuildId, Colour},
+};
+
+use crate::{
+    commands::{
+        send_ephemeral_message,
+        tldr::{cache, Request},
+    },
+    quote::truncate,
+    util::{kvstore, DatabaseTypeMapKey},
+};
+
+// How long the configurable parts may be, so they leave enough of the context for the history
+const MAX_TEMPLATE_LENGTH: usize = 1000;
+const MAX_LANGUAGE_LENGTH: usize = 50;
+// Discord doesn't allow longer messages or embed fields
+const MAX_MESSAGE_LENGTH: usize = 2000;
+const MAX_FIELD_LENGTH: usize = 1024;
+
+const DEFAULT_DIRECTIVE: &str = "You are {name}, a multi-purpose Discord bot that has been tasked with summarizing the recent topics of a Discord conversation. {context} The history that follows is the chat history of this conversation. The current time is {time}. Feel free to use markdown formatting in your response.";
+const DEFAULT_INSTRUCTIONS: &str = "Please summarize the discussed subjects {format}, use usernames where reasonable.";
+
+// What a template can refer to, and what each of them stands for
+const VARIABLES: [(&str, &str); 7] = [
+    ("name", "the bot's name"),
+    ("channel", "the channel, thread or forum post"),
+    ("topic", "the channel's topic"),
+    ("context", "a description of where the conversation takes place"),
+    ("time", "the current time"),
+    ("bullets", "how many points the summary should have at most"),
+    ("format", "the output style, like \"using at most 5 bullet points\""),
+];
+
+/// How the summary is written.
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum Style {
+    #[default]
+    Bullets,
+    Paragraphs,
+    Headlines,
+}
+
+impl Style {
+    fn parse(style: &str) -> Option<Self> {
+        match style {
+            "bullets" => Some(Self::Bullets),
+            "paragraphs" => Some(Self::Paragraphs),
+            "headlines" => Some(Self::Headlines),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Bullets => "bullets",
+            Self::Paragraphs => "paragraphs",
+            Self::Headlines => "headlines",
+        }
+    }
+
+    fn format(self, bullets: usize) -> String {
+        match self {
+            Self::Bullets => format!("using at most {bullets} bullet points"),
+            Self::Paragraphs => format!("in at most {} short paragraphs", bullets.div_ceil(2)),
+            Self::Headlines => format!("as at most {bullets} one-line headlines, without further explanation"),
+        }
+    }
+}
+
+/// How a guild would like its tldrs to be written. Anything that isn't set uses the default.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub(super) struct Prompts {
+    directive: Option<String>,
+    instructions: Option<String>,
+    language: Option<String>,
+    style: Style,
+}
+
+fn key(guild_id: GuildId) -> String {
+    format!("tldr_prompts_{guild_id}")
+}
+
+/// Removes the prompts of a guild, for when the bot is removed from it.
+pub(crate) async fn forget(db: &DatabaseConnection, guild_id: GuildId) -> Result<()> {
+    kvstore::delete(db, &key(guild_id)).await
+}
+
+impl Prompts {
+    pub(super) async fn load(db: &DatabaseConnection, guild_id: GuildId) -> Result<Self> {
+        Ok(kvstore::get(db, &key(guild_id)).await?.unwrap_or_default())
+    }
+
+    /// The system prompt the model is directed with.
+    pub(super) fn directive(&self, variables: &[(&str, String)]) -> String {
+        let mut directive = render(self.directive.as_deref().unwrap_or(DEFAULT_DIRECTIVE), variables);
+        if let Some(language) = &self.language {
+            directive.push_str(&format!(" Always write your response in {language}."));
+        }
+        directive
+    }
+
+    /// What the model is asked to do with the history, after having read it.
+    pub(super) fn instructions(&self, variables: &[(&str, String)], continues: bool) -> String {
+        let mut instructions = render(self.instructions.as_deref().unwrap_or(DEFAULT_INSTRUCTIONS), variables);
+        if continues {
+            instructions.push_str(
+                " Only summarize what was discussed since the previous summary, don't repeat what it already said.",
+            );
+        }
+        instructions
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "Style: {}\nLanguage: {}\nDirective: {}\nInstructions: {}",
+            self.style.as_str(),
+            self.language.as_deref().unwrap_or("whatever the conversation is in"),
+            self.directive.as_deref().map_or("the default".to_string(), |directive| format!("```{directive}```")),
+            self.instructions
+                .as_deref()
+                .map_or("the default".to_string(), |instructions| format!("```{instructions}```")),
+        )
+    }
+}
+
+/// Everything a template can refer to, for a request that will be summarized into at most this many points.
+pub(super) fn variables(
+    ctx: &Context,
+    request: &Request,
+    bullets: usize,
+    prompts: &Prompts,
+) -> Vec<(&'static str, String)> {
+    let topic = request.channel.topic.as_ref().or(request.parent.as_ref().and_then(|parent| parent.topic.as_ref()));
+    vec![
+        ("name", ctx.cache.current_user().name.clone()),
+        ("channel", request.channel.name.clone()),
+        ("topic", topic.cloned().unwrap_or_default()),
+        ("context", request.context()),
+        ("time", Utc::now().to_string()),
+        ("bullets", bullets.to_string()),
+        ("format", prompts.style.format(bullets)),
+    ]
+}
+
+fn render(template: &str, variables: &[(&str, String)]) -> String {
+    variables.iter().fold(template.to_string(), |text, (name, value)| text.replace(&format!("{{{name}}}"), value))
+}
+
+/// Makes sure a template fits, and only refers to variables that exist.
+fn validate(template: &str) -> Result<(), String> {
+    if template.chars().count() > MAX_TEMPLATE_LENGTH {
+        return Err(format!("Templates can be at most {MAX_TEMPLATE_LENGTH} characters long."));
+    }
+    for part in template.split('{').skip(1) {
+        let Some((name, _)) = part.split_once('}') else { continue };
+        if !VARIABLES.iter().any(|(variable, _)| *variable == name) {
+            return Err(format!("There is no variable called {{{name}}}, use one of {}.", variable_list()));
+        }
+    }
+    Ok(())
+}
+
+fn variable_list() -> String {
+    VARIABLES.iter().map(|(name, _)| format!("{{{name}}}")).collect::<Vec<_>>().join(", ")
+}
+
+fn variables_help() -> String {
+    VARIABLES.iter().map(|(name, meaning)| format!("{{{name}}} for {meaning}")).collect::<Vec<_>>().join(", ")
+}
+
+pub(super) fn prompt_options() -> Vec<CreateCommandOption> {
+    vec![
+        CreateCommandOption::new(CommandOptionType::String, "style", "How the summary is written")
+            .add_string_choice("Bullet points", Style::Bullets.as_str())
+            .add_string_choice("Short paragraphs", Style::Paragraphs.as_str())
+            .add_string_choice("Headlines", Style::Headlines.as_str()),
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "language",
+            "The language to write in, or \"default\" for whatever the conversation is in",
+        )
+        .max_length(MAX_LANGUAGE_LENGTH as u16),
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "directive",
+            "Who the bot is and what it's doing, with variables like {channel}, or \"default\"",
+        )
+        .max_length(MAX_TEMPLATE_LENGTH as u16),
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "instructions",
+            "What to do with the history, with variables like {format}, or \"default\"",
+        )
+        .max_length(MAX_TEMPLATE_LENGTH as u16),
+    ]
+}
+
+pub(super) async fn handle_prompt(ctx: Context, cmd: CommandInteraction, options: &[CommandDataOption]) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+
+    let permissions = match cmd.member.as_ref().and_then(|m| m.permissions) {
+        Some(p) => p,
+        None => return Err(anyhow!("Could not fetch member permissions")),
+    };
+    if !permissions.manage_guild() {
+        return send_ephemeral_message(ctx, cmd, "You need the Manage Server permission to change this.").await;
+    }
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let mut prompts = Prompts::load(&db, guild_id).await?;
+
+    for option in options {
+        let CommandDataOptionValue::String(value) = &option.value else { continue };
+        let value = value.trim();
+        // Any of the text can be put back to how it was
+        let custom = (!value.eq_ignore_ascii_case("default")).then(|| value.to_string());
+        match option.name.as_str() {
+            "style" => prompts.style = Style::parse(value).unwrap_or_default(),
+            "language" => prompts.language = custom,
+            "directive" | "instructions" => {
+                if let Err(e) = custom.as_deref().map_or(Ok(()), validate) {
+                    return send_ephemeral_message(ctx, cmd, &e).await;
+                }
+                match option.name.as_str() {
+                    "directive" => prompts.directive = custom,
+                    _ => prompts.instructions = custom,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    kvstore::set(&db, &key(guild_id), &prompts).await?;
+    // Summaries written with the old prompts shouldn't be handed out anymore
+    cache::forget(&db, guild_id).await?;
+    send_ephemeral_message(ctx, cmd, &truncate(&prompts.describe(), MAX_MESSAGE_LENGTH)).await
+}
+
+/// Shows what the model would be told for a tldr of this channel, without asking it anything.
+pub(super) async fn handle_preview(ctx: Context, cmd: CommandInteraction, options: &[CommandDataOption]) -> Result<()> {
+    let request = match Request::parse(&ctx, &cmd, options).await? {
+        Ok(request) => request,
+        Err(e) => return send_ephemeral_message(ctx, cmd, &e).await,
+    };
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let prompts = Prompts::load(&db, request.guild_id).await?;
+    let variables = variables(&ctx, &request, super::bullets(request.max), &prompts);
+
+    let embed = CreateEmbed::new()
+        .title("TLDR prompt preview")
+        .colour(Colour::FABLED_PINK)
+        .description(format!("Templates can use {}.", variables_help()))
+        .field("Directive", truncate(&prompts.directive(&variables), MAX_FIELD_LENGTH), false)
+        .field(
+            "Instructions",
+            truncate(&prompts.instructions(&variables, request.previous.is_some()), MAX_FIELD_LENGTH),
+            false,
+        );
+    cmd.create_response(
+        &ctx,
+        CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().ephemeral(true).embed(embed)),
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, validate, Prompts, Style, MAX_TEMPLATE_LENGTH};
+
+    fn variables() -> Vec<(&'static str, String)> {
+        vec![("channel", "general".to_string()), ("format", "using at most 5 bullet points".to_string())]
+    }
+
+    #[test]
+    fn accepts_known_variables() {
+        assert!(validate("Summarize #{channel} {format}.").is_ok());
+        assert!(validate("No variables at all").is_ok());
+        // A brace that isn't closed isn't a variable
+        assert!(validate("Summarize #{channel} {").is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_variables_and_long_templates() {
+        assert!(validate("Hello {user}").unwrap_err().contains("{user}"));
+        assert!(validate(&"a".repeat(MAX_TEMPLATE_LENGTH + 1)).is_err());
+        assert!(validate(&"a".repeat(MAX_TEMPLATE_LENGTH)).is_ok());
+    }
+
+    #[test]
+    fn renders_every_variable() {
+        assert_eq!(
+            render("Summarize #{channel} {format}, #{channel} only.", &variables()),
+            "Summarize #general using at most 5 bullet points, #general only."
+        );
+        assert_eq!(render("Unknown {topic} stays", &variables()), "Unknown {topic} stays");
+    }
+
+    #[test]
+    fn adds_the_language_and_continuation() {
+        let prompts = Prompts {
+            instructions: Some("Summarize {format}.".to_string()),
+            language: Some("Dutch".to_string()),
+            style: Style::Headlines,
+            ..Default::default()
+        };
+        assert!(prompts.directive(&variables()).ends_with(" Always write your response in Dutch."));
+        assert_eq!(prompts.instructions(&variables(), false), "Summarize using at most 5 bullet points.");
+        assert!(prompts.instructions(&variables(), true).contains("since the previous summary"));
+    }
+
+    #[test]
+    fn describes_the_style() {
+        assert_eq!(Style::Bullets.format(5), "using at most 5 bullet points");
+        assert_eq!(Style::Paragraphs.format(5), "in at most 3 short paragraphs");
+        assert_eq!(Style::parse("headlines").map(Style::as_str), Some("headlines"));
+        assert!(Style::parse("poem").is_none());
+    }
+}
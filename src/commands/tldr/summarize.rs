@@ -0,0 +1,211 @@
+use std::slice::from_ref;
+
+use anyhow::{anyhow, Result};
+use serenity::{
+    all::{CommandInteraction, ComponentInteraction},
+    builder::EditInteractionResponse,
+    client::Context,
+};
+use tiktoken_rs::CoreBPE;
+
+use crate::{
+    commands::tldr::num_tokens_from_messages,
+    llm::{ChatMessage, Llm, Role},
+};
+
+const PART_PROMPT: &str = "Please summarize the discussed subjects in this part of the history using bullet points, use usernames where reasonable and mention roughly when things were discussed.";
+
+/// The interaction a summary gets posted through, either the command itself or a press of its refresh button.
+/// Scheduled digests have nobody waiting on them, so there's nothing to report progress to.
+pub(super) enum Response<'a> {
+    Command(&'a Context, &'a CommandInteraction),
+    Component(&'a Context, &'a ComponentInteraction),
+    Scheduled,
+}
+
+impl Response<'_> {
+    pub(super) async fn edit(&self, builder: EditInteractionResponse) -> Result<()> {
+        match self {
+            Response::Command(ctx, cmd) => cmd.edit_response(ctx, builder).await?,
+            Response::Component(ctx, interaction) => interaction.edit_response(ctx, builder).await?,
+            Response::Scheduled => return Ok(()),
+        };
+        Ok(())
+    }
+}
+
+/// Summarizes a history of any length. If it doesn't fit in a single request, the history is split into parts that do,
+/// every part is summarized on its own, and then those summaries are summarized, until it all fits.
+/// Everything sent and received is added to the tokens used, including the parts that got done before anything failed.
+pub(super) async fn summarize(
+    response: &Response<'_>,
+    llm: &Llm,
+    bpe: &CoreBPE,
+    directive: ChatMessage,
+    mut history: Vec<ChatMessage>,
+    prompt: ChatMessage,
+    tokens_used: &mut u32,
+) -> Result<String> {
+    loop {
+        // Our directive and prompt are always sent along, whatever is left of the context can be filled with history
+        // The configured context size should leave a good margin of error, in case our token calculation differs from the model's
+        let fixed = num_tokens_from_messages(bpe, &[directive.clone(), prompt.clone()])?;
+        let budget = (llm.context_tokens - llm.response_tokens).saturating_sub(fixed);
+        let length = history.len();
+        let mut parts = split(bpe, history, budget)?;
+
+        if parts.len() <= 1 {
+            let mut conversation = vec![directive];
+            conversation.extend(parts.pop().unwrap_or_default());
+            conversation.push(prompt);
+            return complete(llm, bpe, &conversation, tokens_used).await;
+        }
+        // Summaries should be a lot shorter than what they summarize, if they aren't we would never finish
+        if parts.len() >= length {
+            return Err(anyhow!("The context of the model is too small to combine {length} summaries"));
+        }
+
+        let total = parts.len();
+        let mut summaries = Vec::with_capacity(total);
+        for (index, part) in parts.into_iter().enumerate() {
+            progress(response, &format!("Reading through the history, part {} of {total}…", index + 1)).await;
+
+            let mut conversation = vec![directive.clone()];
+            conversation.extend(part);
+            conversation.push(ChatMessage::new(Role::User, PART_PROMPT));
+            let summary = complete(llm, bpe, &conversation, tokens_used).await?;
+            summaries.push(ChatMessage::new(
+                Role::System,
+                format!("Summary of part {} of {total} of the history:\n{summary}", index + 1),
+            ));
+        }
+
+        progress(response, &format!("Combining the summaries of {total} parts…")).await;
+        history = summaries;
+    }
+}
+
+/// Splits the history into consecutive parts that each cost at most the budget.
+fn split(bpe: &CoreBPE, history: Vec<ChatMessage>, budget: u32) -> Result<Vec<Vec<ChatMessage>>> {
+    let mut parts = Vec::new();
+    let mut part = Vec::new();
+    let mut remaining = budget;
+    for message in history {
+        let cost = num_tokens_from_messages(bpe, from_ref(&message))?;
+        if cost > budget {
+            warn!("Skipping a message of {cost} tokens in tldr, it doesn't fit in the context on its own");
+            continue;
+        }
+
+        if cost > remaining {
+            parts.push(std::mem::take(&mut part));
+            remaining = budget;
+        }
+        remaining -= cost;
+        part.push(message);
+    }
+    if !part.is_empty() {
+        parts.push(part);
+    }
+    Ok(parts)
+}
+
+/// Sends the conversation off, and adds what it cost to the tokens used.
+pub(super) async fn complete(
+    llm: &Llm,
+    bpe: &CoreBPE,
+    conversation: &[ChatMessage],
+    tokens_used: &mut u32,
+) -> Result<String> {
+    let completion = llm.complete(conversation).await?;
+    // Go by what the provider says it used, and estimate it ourselves if it doesn't tell us
+    *tokens_used += match completion.usage {
+        Some(usage) => usage,
+        None => {
+            num_tokens_from_messages(bpe, conversation)? + bpe.encode_with_special_tokens(&completion.text).len() as u32
+        }
+    };
+    Ok(completion.text)
+}
+
+/// Lets the user know how far along we are, in the deferred response.
+async fn progress(response: &Response<'_>, content: &str) {
+    if let Err(e) = response.edit(EditInteractionResponse::new().content(content)).await {
+        error!("Could not update tldr progress: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::slice::from_ref;
+
+    use tiktoken_rs::o200k_base;
+
+    use super::{split, summarize, Response};
+    use crate::{
+        commands::tldr::num_tokens_from_messages,
+        llm::{ChatMessage, Llm, Role},
+    };
+
+    fn history(count: usize) -> Vec<ChatMessage> {
+        (0..count).map(|_| ChatMessage::new(Role::User, "someone: hello there")).collect()
+    }
+
+    #[test]
+    fn splits_into_parts_within_the_budget() {
+        let bpe = o200k_base().unwrap();
+        let cost = num_tokens_from_messages(&bpe, from_ref(&history(1)[0])).unwrap();
+
+        let parts = split(&bpe, history(10), cost * 3).unwrap();
+        let sizes: Vec<usize> = parts.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![3, 3, 3, 1]);
+    }
+
+    #[test]
+    fn skips_messages_that_never_fit() {
+        let bpe = o200k_base().unwrap();
+        let cost = num_tokens_from_messages(&bpe, from_ref(&history(1)[0])).unwrap();
+
+        let mut messages = history(2);
+        messages.insert(1, ChatMessage::new(Role::User, "word ".repeat(100)));
+        let parts = split(&bpe, messages, cost * 2).unwrap();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].len(), 2);
+    }
+
+    #[tokio::test]
+    async fn summarizes_a_short_history_at_once() {
+        let bpe = o200k_base().unwrap();
+        let llm = Llm::mock(100000, 1000);
+        let directive = ChatMessage::new(Role::System, "directive");
+        let prompt = ChatMessage::new(Role::User, "prompt");
+
+        let mut tokens_used = 0;
+        let text =
+            summarize(&Response::Scheduled, &llm, &bpe, directive, history(5), prompt, &mut tokens_used).await.unwrap();
+        assert!(text.starts_with("Mock reply to 7 messages:"));
+        assert!(tokens_used > 0);
+    }
+
+    #[tokio::test]
+    async fn summarizes_a_long_history_in_parts() {
+        let bpe = o200k_base().unwrap();
+        let directive = ChatMessage::new(Role::System, "directive");
+        let prompt = ChatMessage::new(Role::User, "prompt");
+
+        // Leave room for a couple of messages longer than any summary the mock writes, but not for the whole history
+        let fixed = num_tokens_from_messages(&bpe, &[directive.clone(), prompt.clone()]).unwrap();
+        let long = num_tokens_from_messages(&bpe, &[ChatMessage::new(Role::User, "a ".repeat(400))]).unwrap();
+        let llm = Llm::mock(1000 + fixed + long * 2, 1000);
+        let history = history(200);
+        assert!(num_tokens_from_messages(&bpe, &history).unwrap() > long * 2);
+
+        let mut tokens_used = 0;
+        let text =
+            summarize(&Response::Scheduled, &llm, &bpe, directive, history, prompt, &mut tokens_used).await.unwrap();
+        // The last request only saw the summaries of the parts
+        assert!(text.contains("- Summary of part 1 of "));
+        assert!(!text.contains("hello there"));
+        assert!(tokens_used > long);
+    }
+}
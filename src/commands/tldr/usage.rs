@@ -0,0 +1,143 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, NaiveDate, Utc};
+use sea_orm::{
+    sea_query::{Alias, Expr, OnConflict},
+    ActiveValue::Set,
+    ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
+};
+use serenity::{
+    all::CommandInteraction,
+    builder::{CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage},
+    client::Context,
+    model::{
+        id::{GuildId, UserId},
+        Colour,
+    },
+    prelude::Mentionable,
+};
+
+use entity::{prelude::TldrUsage, tldr_usage};
+
+use crate::{
+    commands::{send_ephemeral_message, tldr::limits::Limits},
+    util::DatabaseTypeMapKey,
+};
+
+// How many days and members the report lists
+const REPORT_DAYS: usize = 7;
+const REPORT_USERS: usize = 10;
+
+/// Adds the tokens a tldr used to today's spend of the guild and the member that asked for it.
+pub(super) async fn record(db: &DatabaseConnection, guild_id: GuildId, user_id: UserId, tokens: u32) -> Result<()> {
+    let usage = tldr_usage::ActiveModel {
+        server_id: Set(guild_id.get() as i64),
+        user_id: Set(user_id.get() as i64),
+        day: Set(Utc::now().date_naive()),
+        tokens: Set(tokens as i64),
+        requests: Set(1),
+    };
+    let excluded = Alias::new("excluded");
+    TldrUsage::insert(usage)
+        .on_conflict(
+            OnConflict::columns([tldr_usage::Column::ServerId, tldr_usage::Column::UserId, tldr_usage::Column::Day])
+                .value(
+                    tldr_usage::Column::Tokens,
+                    Expr::col((TldrUsage, tldr_usage::Column::Tokens))
+                        .add(Expr::col((excluded.clone(), tldr_usage::Column::Tokens))),
+                )
+                .value(
+                    tldr_usage::Column::Requests,
+                    Expr::col((TldrUsage, tldr_usage::Column::Requests))
+                        .add(Expr::col((excluded, tldr_usage::Column::Requests))),
+                )
+                .to_owned(),
+        )
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+fn start_of_month() -> NaiveDate {
+    let today = Utc::now().date_naive();
+    today.with_day(1).unwrap_or(today)
+}
+
+async fn this_month(db: &DatabaseConnection, guild_id: GuildId) -> Result<Vec<tldr_usage::Model>> {
+    Ok(TldrUsage::find()
+        .filter(tldr_usage::Column::ServerId.eq(guild_id.get()))
+        .filter(tldr_usage::Column::Day.gte(start_of_month()))
+        .all(db)
+        .await?)
+}
+
+/// How many tokens the guild has spent on tldrs this month.
+pub(super) async fn month_total(db: &DatabaseConnection, guild_id: GuildId) -> Result<u64> {
+    Ok(this_month(db, guild_id).await?.iter().map(|usage| usage.tokens.max(0) as u64).sum())
+}
+
+pub(super) async fn handle_usage(ctx: Context, cmd: CommandInteraction) -> Result<()> {
+    let Some(guild_id) = cmd.guild_id else { return Ok(()) };
+
+    let permissions = match cmd.member.as_ref().and_then(|m| m.permissions) {
+        Some(p) => p,
+        None => return Err(anyhow!("Could not fetch member permissions")),
+    };
+    if !permissions.manage_guild() {
+        return send_ephemeral_message(ctx, cmd, "You need the Manage Server permission to see this.").await;
+    }
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    let limits = Limits::load(&db, guild_id).await?;
+    let usage = this_month(&db, guild_id).await?;
+
+    let mut total = (0, 0);
+    let mut days: BTreeMap<NaiveDate, (i64, i32)> = BTreeMap::new();
+    let mut users: HashMap<i64, (i64, i32)> = HashMap::new();
+    for row in &usage {
+        for (tokens, requests) in [&mut total, days.entry(row.day).or_default(), users.entry(row.user_id).or_default()]
+        {
+            *tokens += row.tokens;
+            *requests += row.requests;
+        }
+    }
+
+    let budget = match limits.monthly_budget {
+        Some(budget) => format!(" of the {budget} token budget"),
+        None => String::new(),
+    };
+    let days: Vec<String> = days
+        .iter()
+        .rev()
+        .take(REPORT_DAYS)
+        .map(|(day, (tokens, requests))| format!("{day}: {tokens} tokens over {requests} tldrs"))
+        .collect();
+    let mut users: Vec<(i64, (i64, i32))> = users.into_iter().collect();
+    users.sort_by(|(_, (a, _)), (_, (b, _))| b.cmp(a));
+    let users: Vec<String> = users
+        .iter()
+        .take(REPORT_USERS)
+        .map(|(user_id, (tokens, requests))| {
+            format!("{}: {tokens} tokens over {requests} tldrs", UserId::new(*user_id as u64).mention())
+        })
+        .collect();
+
+    let mut embed = CreateEmbed::new()
+        .title("TLDR usage this month")
+        .colour(Colour::FABLED_PINK)
+        .description(format!("{} tokens{budget}, over {} tldrs.", total.0, total.1));
+    if !days.is_empty() {
+        embed = embed.field("Recent days", days.join("\n"), false);
+    }
+    if !users.is_empty() {
+        embed = embed.field("Top members", users.join("\n"), false);
+    }
+
+    cmd.create_response(
+        &ctx,
+        CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().ephemeral(true).embed(embed)),
+    )
+    .await?;
+    Ok(())
+}
@@ -1,13 +1,37 @@
+use std::env::var;
+
 use anyhow::{anyhow, Result};
-use sea_orm::{ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter};
+use sea_orm::{
+    sea_query::{Expr, SimpleExpr},
+    ActiveModelTrait,
+    ActiveValue::Set,
+    ColumnTrait, Condition, DatabaseConnection, EntityTrait, IntoActiveModel, QueryFilter,
+};
 use serenity::{
     client::Context,
-    model::id::{GuildId, RoleId},
+    model::{
+        channel::GuildChannel,
+        guild::UnavailableGuild,
+        id::{GuildId, RoleId},
+    },
 };
 
-use entity::{prelude::RoleButtonServer, role_button_server};
+use entity::{
+    ai_audit, ai_opt_out, archive_channel, archived_message,
+    prelude::{
+        AiAudit, AiOptOut, ArchiveChannel, ArchivedMessage, Quote, QuotePurge, Readycheck, ReadycheckSchedule,
+        RoleButtonServer, TldrDigest, TldrSummary, TldrUsage,
+    },
+    quote, quote_purge, readycheck, readycheck_schedule, role_button_server, tldr_digest, tldr_summary, tldr_usage,
+};
 
-use crate::{commands::rolebutton_post_check_for_update, util::DatabaseTypeMapKey};
+use crate::{
+    commands::{
+        counter_purge, readycheck_forget_config, rolebutton_post_check_for_update, tldr_forget_limits,
+        tldr_forget_prompts,
+    },
+    util::DatabaseTypeMapKey,
+};
 
 pub(crate) async fn guild_role_delete(ctx: Context, guild_id: GuildId, role_id: RoleId) -> Result<()> {
     let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
@@ -45,3 +69,124 @@ pub(crate) async fn guild_role_delete(ctx: Context, guild_id: GuildId, role_id:
 
     Ok(())
 }
+
+pub(crate) async fn channel_update(ctx: Context, channel: GuildChannel) -> Result<()> {
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    Quote::update_many()
+        .col_expr(quote::Column::ChannelName, Expr::value(channel.name.clone()))
+        .filter(quote::Column::ChannelId.eq(channel.id.get()))
+        .filter(quote::Column::ChannelName.ne(channel.name))
+        .exec(&db)
+        .await?;
+    Ok(())
+}
+
+pub(crate) async fn channel_delete(ctx: Context, channel: GuildChannel) -> Result<()> {
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    Quote::update_many()
+        .col_expr(quote::Column::ChannelArchived, Expr::value(true))
+        .filter(quote::Column::ChannelId.eq(channel.id.get()))
+        .exec(&db)
+        .await?;
+    // Scheduled readychecks can't be posted in a channel that no longer exists
+    ReadycheckSchedule::delete_many()
+        .filter(readycheck_schedule::Column::ChannelId.eq(channel.id.get()))
+        .exec(&db)
+        .await?;
+    // There is nothing left to archive, and nowhere left to jump to
+    ArchiveChannel::delete_by_id(channel.id.get() as i64).exec(&db).await?;
+    ArchivedMessage::delete_many().filter(archived_message::Column::ChannelId.eq(channel.id.get())).exec(&db).await?;
+    TldrSummary::delete_many().filter(tldr_summary::Column::ChannelId.eq(channel.id.get())).exec(&db).await?;
+    // A digest can't be written about, or posted to, a channel that's gone
+    TldrDigest::delete_many()
+        .filter(
+            Condition::any()
+                .add(tldr_digest::Column::ChannelId.eq(channel.id.get()))
+                .add(tldr_digest::Column::DigestChannelId.eq(channel.id.get())),
+        )
+        .exec(&db)
+        .await?;
+    AiOptOut::delete_many().filter(ai_opt_out::Column::TargetId.eq(channel.id.get())).exec(&db).await?;
+    clear_rolebutton_posts(&db, role_button_server::Column::PostChannelId.eq(channel.id.get())).await
+}
+
+/// What to do with the data of a guild the bot got removed from, configured through `GUILD_DELETE_MODE`.
+enum GuildDeleteMode {
+    // Leave everything as-is, in case the bot gets added back
+    Keep,
+    // Keep the quotes, but mark them as archived and forget about posts we can no longer reach
+    Archive,
+    // Remove everything we know about the guild
+    Purge,
+}
+
+pub(crate) async fn guild_delete(ctx: Context, guild: UnavailableGuild) -> Result<()> {
+    // An unavailable guild is an outage, not a removal, so there is nothing to clean up
+    if guild.unavailable {
+        return Ok(());
+    }
+
+    let mode = match var("GUILD_DELETE_MODE").as_deref() {
+        Err(_) | Ok("") | Ok("keep") => GuildDeleteMode::Keep,
+        Ok("archive") => GuildDeleteMode::Archive,
+        Ok("purge") => GuildDeleteMode::Purge,
+        Ok(mode) => return Err(anyhow!("Unknown GUILD_DELETE_MODE {mode}, expected keep, archive or purge")),
+    };
+
+    let db = ctx.data.read().await.get::<DatabaseTypeMapKey>().unwrap().clone();
+    match mode {
+        GuildDeleteMode::Keep => {}
+        GuildDeleteMode::Archive => {
+            info!("Removed from guild {}, archiving its quotes", guild.id);
+            Quote::update_many()
+                .col_expr(quote::Column::ChannelArchived, Expr::value(true))
+                .filter(quote::Column::ServerId.eq(guild.id.get()))
+                .exec(&db)
+                .await?;
+            clear_rolebutton_posts(&db, role_button_server::Column::ServerId.eq(guild.id.get())).await?;
+        }
+        GuildDeleteMode::Purge => {
+            info!("Removed from guild {}, purging all of its data", guild.id);
+            Quote::delete_many().filter(quote::Column::ServerId.eq(guild.id.get())).exec(&db).await?;
+            QuotePurge::delete_many().filter(quote_purge::Column::ServerId.eq(guild.id.get())).exec(&db).await?;
+            Readycheck::delete_many().filter(readycheck::Column::ServerId.eq(guild.id.get())).exec(&db).await?;
+            ReadycheckSchedule::delete_many()
+                .filter(readycheck_schedule::Column::ServerId.eq(guild.id.get()))
+                .exec(&db)
+                .await?;
+            readycheck_forget_config(&db, guild.id).await?;
+            tldr_forget_limits(&db, guild.id).await?;
+            tldr_forget_prompts(&db, guild.id).await?;
+            RoleButtonServer::delete_many()
+                .filter(role_button_server::Column::ServerId.eq(guild.id.get()))
+                .exec(&db)
+                .await?;
+            TldrUsage::delete_many().filter(tldr_usage::Column::ServerId.eq(guild.id.get())).exec(&db).await?;
+            TldrSummary::delete_many().filter(tldr_summary::Column::ServerId.eq(guild.id.get())).exec(&db).await?;
+            TldrDigest::delete_many().filter(tldr_digest::Column::ServerId.eq(guild.id.get())).exec(&db).await?;
+            AiOptOut::delete_many().filter(ai_opt_out::Column::ServerId.eq(guild.id.get())).exec(&db).await?;
+            AiAudit::delete_many().filter(ai_audit::Column::ServerId.eq(guild.id.get())).exec(&db).await?;
+            counter_purge(&db, guild.id).await?;
+            ArchiveChannel::delete_many()
+                .filter(archive_channel::Column::ServerId.eq(guild.id.get()))
+                .exec(&db)
+                .await?;
+            ArchivedMessage::delete_many()
+                .filter(archived_message::Column::ServerId.eq(guild.id.get()))
+                .exec(&db)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn clear_rolebutton_posts(db: &DatabaseConnection, filter: SimpleExpr) -> Result<()> {
+    RoleButtonServer::update_many()
+        .col_expr(role_button_server::Column::PostChannelId, Expr::value(Option::<i64>::None))
+        .col_expr(role_button_server::Column::PostMessageId, Expr::value(Option::<i64>::None))
+        .filter(filter)
+        .exec(db)
+        .await?;
+    Ok(())
+}
@@ -1,18 +1,25 @@
 use serenity::{
-    all::{ComponentInteraction, Interaction},
+    all::{ComponentInteraction, Interaction, ModalInteraction},
     client::{Context, EventHandler},
     gateway::ActivityData,
     model::{
-        channel::{Message, Reaction, ReactionType},
+        channel::{GuildChannel, Message, Reaction, ReactionType},
+        event::MessageUpdateEvent,
         gateway::Ready,
-        guild::Role,
-        id::{GuildId, RoleId},
+        guild::{Guild, Role, UnavailableGuild},
+        id::{ChannelId, GuildId, MessageId, RoleId},
+        voice::VoiceState,
     },
 };
 use tokio::{join, sync::broadcast};
 
 use crate::{
-    commands::{handle_ccounter_ingress, handle_command, introduce_commands, mia_press_loop, rolebutton_press_loop},
+    archive,
+    commands::{
+        handle_autocomplete, handle_command, handle_counter_ingress, introduce_commands, mia_press_loop,
+        readycheck_press_loop, readycheck_resume, readycheck_voice_state_update, rolebutton_press_loop,
+        tldr_digest_run, tldr_refresh_press_loop,
+    },
     db_integrity,
     ingest::reaction,
 };
@@ -21,23 +28,50 @@ const QUOTE_REACTION: &str = "💬";
 
 pub(crate) struct Handler {
     component_interactions: broadcast::Sender<(Context, ComponentInteraction)>,
+    modal_interactions: broadcast::Sender<(Context, ModalInteraction)>,
 }
 
 impl Handler {
     pub fn new() -> Self {
-        let (sender, rolebutton_recv) = broadcast::channel(16);
+        // Large readychecks can get a burst of presses at once, which every loop has to keep up with
+        let (sender, rolebutton_recv) = broadcast::channel(256);
         tokio::spawn(rolebutton_press_loop(rolebutton_recv));
         tokio::spawn(mia_press_loop(sender.subscribe()));
-        Self { component_interactions: sender }
+        tokio::spawn(readycheck_press_loop(sender.subscribe()));
+        tokio::spawn(tldr_refresh_press_loop(sender.subscribe()));
+        let (modal_sender, _) = broadcast::channel(16);
+        Self { component_interactions: sender, modal_interactions: modal_sender }
     }
 
     pub fn subscribe_to_component_interactions(&self) -> broadcast::Receiver<(Context, ComponentInteraction)> {
         self.component_interactions.subscribe()
     }
+
+    pub fn subscribe_to_modal_interactions(&self) -> broadcast::Receiver<(Context, ModalInteraction)> {
+        self.modal_interactions.subscribe()
+    }
 }
 
 #[serenity::async_trait]
 impl EventHandler for Handler {
+    async fn channel_delete(&self, ctx: Context, channel: GuildChannel, _messages: Option<Vec<Message>>) {
+        if let Err(e) = db_integrity::channel_delete(ctx, channel).await {
+            error!("Could not perform DB integrity on channel deletion: {}", e);
+        }
+    }
+
+    async fn channel_update(&self, ctx: Context, _old: Option<GuildChannel>, new: GuildChannel) {
+        if let Err(e) = db_integrity::channel_update(ctx, new).await {
+            error!("Could not perform DB integrity on channel update: {}", e);
+        }
+    }
+
+    async fn guild_delete(&self, ctx: Context, incomplete: UnavailableGuild, _full: Option<Guild>) {
+        if let Err(e) = db_integrity::guild_delete(ctx, incomplete).await {
+            error!("Could not perform DB integrity on guild deletion: {}", e);
+        }
+    }
+
     async fn guild_role_delete(&self, ctx: Context, guild_id: GuildId, role_id: RoleId, _old_role: Option<Role>) {
         if let Err(e) = db_integrity::guild_role_delete(ctx, guild_id, role_id).await {
             error!("Could not perform DB integrity on role deletion: {}", e);
@@ -45,8 +79,47 @@ impl EventHandler for Handler {
     }
 
     async fn message(&self, ctx: Context, msg: Message) {
-        if let Err(e) = handle_ccounter_ingress(&ctx, &msg).await {
-            error!("Could not handle ccounter ingress: {}", e);
+        if let Err(e) = handle_counter_ingress(&ctx, &msg).await {
+            error!("Could not count message: {}", e);
+        }
+        if let Err(e) = archive::message(&ctx, &msg).await {
+            error!("Could not archive message: {}", e);
+        }
+    }
+
+    async fn message_update(
+        &self,
+        ctx: Context,
+        _old: Option<Message>,
+        new: Option<Message>,
+        event: MessageUpdateEvent,
+    ) {
+        if let Err(e) = archive::message_update(&ctx, new.as_ref(), &event).await {
+            error!("Could not update archived message: {}", e);
+        }
+    }
+
+    async fn message_delete(
+        &self,
+        ctx: Context,
+        _channel_id: ChannelId,
+        deleted_message_id: MessageId,
+        _guild_id: Option<GuildId>,
+    ) {
+        if let Err(e) = archive::message_delete(&ctx, &[deleted_message_id]).await {
+            error!("Could not delete archived message: {}", e);
+        }
+    }
+
+    async fn message_delete_bulk(
+        &self,
+        ctx: Context,
+        _channel_id: ChannelId,
+        multiple_deleted_messages_ids: Vec<MessageId>,
+        _guild_id: Option<GuildId>,
+    ) {
+        if let Err(e) = archive::message_delete(&ctx, &multiple_deleted_messages_ids).await {
+            error!("Could not delete archived messages: {}", e);
         }
     }
 
@@ -69,6 +142,9 @@ impl EventHandler for Handler {
 
     async fn ready(&self, ctx: Context, _ready: Ready) {
         info!("Bot connected!");
+        tokio::spawn(readycheck_resume(ctx.clone()));
+        tokio::spawn(archive::prune(ctx.clone()));
+        tokio::spawn(tldr_digest_run(ctx.clone()));
         if let Err(e) = introduce_commands(&ctx).await {
             error!("Could not register global commands: {}", e);
         }
@@ -76,6 +152,12 @@ impl EventHandler for Handler {
         ctx.shard.set_activity(Some(ActivityData::playing("in therapy")));
     }
 
+    async fn voice_state_update(&self, ctx: Context, old: Option<VoiceState>, new: VoiceState) {
+        if let Err(e) = readycheck_voice_state_update(ctx, old, new).await {
+            error!("Could not handle voice state update for readychecks: {}", e);
+        }
+    }
+
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
         match interaction {
             Interaction::Command(cmd) => {
@@ -83,11 +165,20 @@ impl EventHandler for Handler {
                     error!("Could not handle command: {}", e);
                 }
             }
+            Interaction::Autocomplete(cmd) => {
+                if let Err(e) = handle_autocomplete(ctx, cmd).await {
+                    error!("Could not handle autocomplete: {}", e);
+                }
+            }
             Interaction::Component(int) => {
                 if let Err(e) = self.component_interactions.send((ctx, int)) {
                     error!("Could not handle component interaction: {e}");
                 }
             }
+            Interaction::Modal(int) => {
+                // Modals are only listened to while someone is filling one in, so nobody listening is no error
+                let _ = self.modal_interactions.send((ctx, int));
+            }
             _ => {}
         }
     }
@@ -62,6 +62,7 @@ async fn ingest(
         author_image: avatar,
         attachment,
         attachment_name,
+        channel_archived: Set(false),
     }
     .insert(&db)
     .await?;
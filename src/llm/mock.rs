@@ -0,0 +1,42 @@
+use anyhow::Result;
+use serenity::futures::future::{self, BoxFuture};
+
+use crate::llm::{ChatMessage, Completion, Provider};
+
+// How much of every message we repeat back
+const PREVIEW_LENGTH: usize = 50;
+
+/// Replies without calling out to anything, always giving the same answer to the same conversation.
+/// Handy for trying out the bot without an API key or a local model.
+pub(super) struct Mock;
+
+impl Provider for Mock {
+    fn complete<'a>(&'a self, messages: &'a [ChatMessage]) -> BoxFuture<'a, Result<Completion>> {
+        let mut reply = format!("Mock reply to {} messages:", messages.len());
+        for message in messages.iter().skip(1).take(5) {
+            let preview: String = message.content.chars().take(PREVIEW_LENGTH).collect();
+            reply.push_str(&format!("\n- {preview}"));
+        }
+        Box::pin(future::ready(Ok(Completion { text: reply, usage: None })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::{ChatMessage, Llm, Role};
+
+    #[tokio::test]
+    async fn replies_the_same_to_the_same_conversation() {
+        let llm = Llm::mock(4096, 512);
+        let conversation = [
+            ChatMessage::new(Role::System, "You are a bot"),
+            ChatMessage::new(Role::User, "first"),
+            ChatMessage::new(Role::User, "second"),
+        ];
+        let first = llm.complete(&conversation).await.unwrap();
+        let second = llm.complete(&conversation).await.unwrap();
+        assert_eq!(first.text, second.text);
+        assert_eq!(first.text, "Mock reply to 3 messages:\n- first\n- second");
+        assert!(first.usage.is_none());
+    }
+}
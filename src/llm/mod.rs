@@ -0,0 +1,125 @@
+use std::{env::var, time::Duration};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serenity::futures::future::BoxFuture;
+
+use crate::llm::{mock::Mock, openai::OpenAi};
+
+mod mock;
+mod openai;
+
+// What we assume an image costs, the OpenAI API charges this for every image sent in low detail
+pub(crate) const IMAGE_TOKENS: u32 = 85;
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Role {
+    System,
+    User,
+}
+
+#[derive(Clone)]
+pub(crate) struct ChatMessage {
+    pub(crate) role: Role,
+    pub(crate) content: String,
+    // Links to images that belong with the message, only sent along to models with vision
+    pub(crate) images: Vec<String>,
+}
+
+impl ChatMessage {
+    pub(crate) fn new(role: Role, content: impl Into<String>) -> Self {
+        Self { role, content: content.into(), images: Vec::new() }
+    }
+}
+
+/// The reply to a conversation.
+pub(crate) struct Completion {
+    pub(crate) text: String,
+    // How many tokens the request took in total, if the provider tells us
+    pub(crate) usage: Option<u32>,
+}
+
+/// Something that can continue a conversation, like the OpenAI API or a local model.
+pub(crate) trait Provider: Send + Sync {
+    /// Sends the conversation off, and returns the reply to it.
+    fn complete<'a>(&'a self, messages: &'a [ChatMessage]) -> BoxFuture<'a, Result<Completion>>;
+}
+
+/// The configured provider, together with what the model it runs can handle.
+pub(crate) struct Llm {
+    provider: Box<dyn Provider>,
+    // How many tokens fit in a single request, including the response
+    pub(crate) context_tokens: u32,
+    // How many tokens we allow the response to take up
+    pub(crate) response_tokens: u32,
+    // Whether the model can look at images
+    pub(crate) vision: bool,
+}
+
+impl Llm {
+    /// Configures the provider from the environment:
+    /// - `LLM_PROVIDER`: `openai` (default), `openai-compatible` or `mock`
+    /// - `LLM_BASE_URL`: where the OpenAI-compatible server is running, like `http://localhost:8080/v1`
+    /// - `LLM_API_KEY`: required for `openai`, optional otherwise (falls back to `CHATGPT_TOKEN`)
+    /// - `LLM_MODEL`, `LLM_TEMPERATURE`, `LLM_TIMEOUT` (in seconds)
+    /// - `LLM_CONTEXT_TOKENS`, `LLM_RESPONSE_TOKENS`
+    /// - `LLM_VISION`: `true` if the model can look at images, which are then sent along
+    pub(crate) fn from_env() -> Result<Self> {
+        let context_tokens = parse_env("LLM_CONTEXT_TOKENS", 9500)?;
+        let response_tokens = parse_env("LLM_RESPONSE_TOKENS", 2048)?;
+        if response_tokens >= context_tokens {
+            return Err(anyhow!("LLM_RESPONSE_TOKENS has to be less than LLM_CONTEXT_TOKENS"));
+        }
+
+        let vision = parse_env("LLM_VISION", false)?;
+        let temperature = parse_env("LLM_TEMPERATURE", 1.0)?;
+        let timeout = Duration::from_secs(parse_env("LLM_TIMEOUT", 60)?);
+        let api_key = var("LLM_API_KEY").or_else(|_| var("CHATGPT_TOKEN")).ok().filter(|key| !key.is_empty());
+
+        let provider: Box<dyn Provider> = match var("LLM_PROVIDER").as_deref().unwrap_or("openai") {
+            "openai" => Box::new(OpenAi::new(
+                var("LLM_BASE_URL").unwrap_or_else(|_| "https://api.openai.com/v1".to_string()),
+                Some(api_key.ok_or_else(|| anyhow!("LLM_API_KEY is required for the openai provider"))?),
+                var("LLM_MODEL").unwrap_or_else(|_| "gpt-5-nano".to_string()),
+                temperature,
+                timeout,
+                response_tokens,
+                false,
+            )?),
+            "openai-compatible" => Box::new(OpenAi::new(
+                var("LLM_BASE_URL")
+                    .map_err(|_| anyhow!("LLM_BASE_URL is required for the openai-compatible provider"))?,
+                api_key,
+                var("LLM_MODEL").map_err(|_| anyhow!("LLM_MODEL is required for the openai-compatible provider"))?,
+                temperature,
+                timeout,
+                response_tokens,
+                true,
+            )?),
+            "mock" => Box::new(Mock),
+            provider => return Err(anyhow!("Unknown LLM provider: {provider}")),
+        };
+
+        Ok(Self { provider, context_tokens, response_tokens, vision })
+    }
+
+    pub(crate) async fn complete(&self, messages: &[ChatMessage]) -> Result<Completion> {
+        self.provider.complete(messages).await
+    }
+}
+
+#[cfg(test)]
+impl Llm {
+    /// The mock provider, as if it ran a model with this much context.
+    pub(crate) fn mock(context_tokens: u32, response_tokens: u32) -> Self {
+        Self { provider: Box::new(Mock), context_tokens, response_tokens, vision: false }
+    }
+}
+
+fn parse_env<T: std::str::FromStr>(key: &str, default: T) -> Result<T> {
+    match var(key) {
+        Ok(value) => value.parse().map_err(|_| anyhow!("Could not parse {key}: {value}")),
+        Err(_) => Ok(default),
+    }
+}
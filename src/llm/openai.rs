@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serenity::futures::future::BoxFuture;
+
+use crate::llm::{ChatMessage, Completion, Provider, Role};
+
+/// The OpenAI chat completions API, or any server that mimics it (llama.cpp, ollama, vLLM, ...).
+pub(super) struct OpenAi {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    temperature: f32,
+    response_tokens: u32,
+    // OpenAI's newer models only take max_completion_tokens, while most local servers only know max_tokens
+    legacy_max_tokens: bool,
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    model: &'a str,
+    messages: Vec<Message<'a>>,
+    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_completion_tokens: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct Message<'a> {
+    role: Role,
+    content: Content<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Content<'a> {
+    Text(&'a str),
+    Parts(Vec<Part<'a>>),
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Part<'a> {
+    Text { text: &'a str },
+    ImageUrl { image_url: ImageUrl<'a> },
+}
+
+#[derive(Serialize)]
+struct ImageUrl<'a> {
+    url: &'a str,
+    detail: &'static str,
+}
+
+impl<'a> From<&'a ChatMessage> for Message<'a> {
+    fn from(message: &'a ChatMessage) -> Self {
+        if message.images.is_empty() {
+            return Self { role: message.role, content: Content::Text(&message.content) };
+        }
+
+        let mut parts = vec![Part::Text { text: &message.content }];
+        parts.extend(message.images.iter().map(|url| Part::ImageUrl { image_url: ImageUrl { url, detail: "low" } }));
+        // Only the user is allowed to send images
+        Self { role: Role::User, content: Content::Parts(parts) }
+    }
+}
+
+#[derive(Deserialize)]
+struct Response {
+    choices: Vec<Choice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+struct Usage {
+    total_tokens: u32,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ResponseMessage,
+}
+
+#[derive(Deserialize)]
+struct ResponseMessage {
+    content: Option<String>,
+}
+
+impl OpenAi {
+    pub(super) fn new(
+        base_url: String,
+        api_key: Option<String>,
+        model: String,
+        temperature: f32,
+        timeout: Duration,
+        response_tokens: u32,
+        legacy_max_tokens: bool,
+    ) -> Result<Self> {
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+        let base_url = base_url.trim_end_matches('/').to_string();
+        Ok(Self { client, base_url, api_key, model, temperature, response_tokens, legacy_max_tokens })
+    }
+
+    async fn send(&self, messages: &[ChatMessage]) -> Result<Completion> {
+        let request = Request {
+            model: &self.model,
+            messages: messages.iter().map(Message::from).collect(),
+            temperature: self.temperature,
+            max_tokens: self.legacy_max_tokens.then_some(self.response_tokens),
+            max_completion_tokens: (!self.legacy_max_tokens).then_some(self.response_tokens),
+        };
+
+        let mut builder = self.client.post(format!("{}/chat/completions", self.base_url)).json(&request);
+        if let Some(api_key) = &self.api_key {
+            builder = builder.bearer_auth(api_key);
+        }
+
+        let response = builder.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(anyhow!("LLM request failed with {status}: {}", response.text().await.unwrap_or_default()));
+        }
+
+        let response: Response = response.json().await?;
+        let text = response
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .ok_or_else(|| anyhow!("LLM responded without a message"))?;
+        Ok(Completion { text, usage: response.usage.map(|usage| usage.total_tokens) })
+    }
+}
+
+impl Provider for OpenAi {
+    fn complete<'a>(&'a self, messages: &'a [ChatMessage]) -> BoxFuture<'a, Result<Completion>> {
+        Box::pin(self.send(messages))
+    }
+}
@@ -1,30 +1,29 @@
 #[macro_use]
 extern crate tracing;
 
-use std::{env, sync::Arc, time::Duration};
+use std::{env, sync::Arc};
 
-use chatgpt::{
-    client::ChatGPT,
-    config::{ChatGPTEngine, ModelConfigurationBuilder},
-};
 use rs_utils::{exit_on_anyhow_error, exit_on_error, get_env_exit, wait_for_signal};
 use sea_orm::Database;
 use serenity::{cache, client::ClientBuilder, model::id::GuildId, prelude::GatewayIntents};
 use tiktoken_rs::o200k_base;
-use tokio::{select, sync::Mutex};
+use tokio::select;
 
 use migration::{Migrator, MigratorTrait};
 
 use crate::{
     handler::Handler,
-    util::{DatabaseTypeMapKey, TLDRTypeMapKey, TLDRUsageStatus::Unused},
+    llm::Llm,
+    util::{DatabaseTypeMapKey, TLDRTypeMapKey},
     web::auth::Client,
 };
 
+mod archive;
 mod commands;
 mod db_integrity;
 mod handler;
 mod ingest;
+mod llm;
 mod quote;
 mod util;
 mod web;
@@ -58,7 +57,8 @@ async fn main() {
                 | GatewayIntents::GUILD_MESSAGE_REACTIONS
                 | GatewayIntents::MESSAGE_CONTENT
                 | GatewayIntents::GUILDS
-                | GatewayIntents::GUILD_MEMBERS,
+                | GatewayIntents::GUILD_MEMBERS
+                | GatewayIntents::GUILD_VOICE_STATES,
         )
             .event_handler(Handler::new())
             .cache_settings(settings)
@@ -66,14 +66,7 @@ async fn main() {
         exit_on_error(discord_client, "Could not create discord client")
     };
 
-    let chatgpt = exit_on_error(ChatGPT::new_with_config(
-        get_env_exit("CHATGPT_TOKEN"),
-        exit_on_error(ModelConfigurationBuilder::default()
-                          .engine(ChatGPTEngine::Custom("gpt-5-nano"))
-                          .temperature(1.0)
-                          .timeout(Duration::from_secs(60))
-                          .build(), "Could not build ChatGPT model configuration"),
-    ), "Could not initialize ChatGPT client");
+    let llm = exit_on_anyhow_error(Llm::from_env(), "Could not configure the LLM provider");
 
     {
         let client_id = get_env_exit("OAUTH_CLIENT");
@@ -99,7 +92,7 @@ async fn main() {
     {
         let mut data = discord_client.data.write().await;
         data.insert::<DatabaseTypeMapKey>(database);
-        data.insert::<TLDRTypeMapKey>((Arc::new(chatgpt), Arc::new(exit_on_anyhow_error(o200k_base(), "Could not initialise tokenizer")), Arc::new(Mutex::new(Unused))));
+        data.insert::<TLDRTypeMapKey>((Arc::new(llm), Arc::new(exit_on_anyhow_error(o200k_base(), "Could not initialise tokenizer"))));
     }
 
     info!("Setup complete. Starting bot...");
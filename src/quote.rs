@@ -1,4 +1,9 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    sea_query::{Alias, Expr, ExprTrait, Func, LikeExpr},
+    ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Select,
+};
 use serenity::{
     all::{CommandInteraction, CreateInteractionResponse},
     builder::{CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, CreateInteractionResponseMessage, CreateMessage},
@@ -6,15 +11,89 @@ use serenity::{
     model::Colour,
     model::{
         channel::Channel,
-        id::{ChannelId, UserId},
+        id::{ChannelId, GuildId, UserId},
     },
     prelude::Mentionable,
 };
 
-use entity::quote;
+use entity::{prelude::Quote, quote};
 
 use crate::util::convert_bytes_to_attachment;
 
+/// A set of criteria to narrow down the quotes of a guild, shared by every command that selects quotes in bulk.
+#[derive(Default, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct QuoteFilter {
+    pub user: Option<UserId>,
+    pub channel: Option<ChannelId>,
+    pub keyword: Option<String>,
+    pub before: Option<DateTime<Utc>>,
+    pub after: Option<DateTime<Utc>>,
+    pub has_image: Option<bool>,
+}
+
+impl QuoteFilter {
+    pub fn is_empty(&self) -> bool {
+        self.user.is_none()
+            && self.channel.is_none()
+            && self.keyword.is_none()
+            && self.before.is_none()
+            && self.after.is_none()
+            && self.has_image.is_none()
+    }
+
+    /// Describes the filter in plain text, e.g. "by @user in #channel containing "keyword"".
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(user) = self.user {
+            parts.push(format!("by {}", user.mention()));
+        }
+        if let Some(channel) = self.channel {
+            parts.push(format!("in {}", channel.mention()));
+        }
+        if let Some(keyword) = &self.keyword {
+            parts.push(format!("containing \"{keyword}\""));
+        }
+        if let Some(after) = self.after {
+            parts.push(format!("from {} onwards", after.format("%Y-%m-%d")));
+        }
+        if let Some(before) = self.before {
+            parts.push(format!("from before {}", before.format("%Y-%m-%d")));
+        }
+        match self.has_image {
+            Some(true) => parts.push("with an image".to_string()),
+            Some(false) => parts.push("without an image".to_string()),
+            None => {}
+        }
+        parts.join(" ")
+    }
+
+    pub fn apply(&self, guild_id: GuildId, mut query: Select<Quote>) -> Select<Quote> {
+        query = query.filter(quote::Column::ServerId.eq(guild_id.get()));
+        if let Some(user) = self.user {
+            query = query.filter(quote::Column::AuthorId.eq(user.get()));
+        }
+        if let Some(channel) = self.channel {
+            query = query.filter(quote::Column::ChannelId.eq(channel.get()));
+        }
+        if let Some(keyword) = &self.keyword {
+            let pattern = like_pattern("%", &keyword.to_lowercase(), "%");
+            query = query.filter(Func::lower(Expr::col((quote::Entity, quote::Column::Text))).like(pattern));
+        }
+        if let Some(before) = self.before {
+            query = query.filter(quote::Column::Timestamp.lt(before));
+        }
+        if let Some(after) = self.after {
+            query = query.filter(quote::Column::Timestamp.gte(after));
+        }
+        match self.has_image {
+            Some(true) => query = query.filter(quote::Column::Attachment.is_not_null()),
+            Some(false) => query = query.filter(quote::Column::Attachment.is_null()),
+            None => {}
+        }
+        query
+    }
+}
+
 pub(crate) async fn post_quote(
     ctx: &Context,
     quote: quote::Model,
@@ -30,16 +109,17 @@ pub(crate) async fn post_quote(
     let image_name = quote.attachment_name.unwrap_or_else(|| "unknown.png".to_string());
     let image = quote.attachment.map(|d| convert_bytes_to_attachment(&image_name, d));
 
-    let channel_name =
-        if let Ok(Channel::Guild(guild_channel)) = ChannelId::from(quote.channel_id as u64).to_channel(&ctx).await {
-            if let Some(message_id) = quote.message_id {
-                format!("https://discord.com/channels/{}/{}/{}", quote.server_id, quote.channel_id, message_id)
-            } else {
-                guild_channel.mention().to_string()
-            }
+    let channel_name = if quote.channel_archived {
+        format!("#{} (archived)", quote.channel_name)
+    } else if let Ok(Channel::Guild(guild_channel)) = ChannelId::from(quote.channel_id as u64).to_channel(&ctx).await {
+        if let Some(message_id) = quote.message_id {
+            format!("https://discord.com/channels/{}/{}/{}", quote.server_id, quote.channel_id, message_id)
         } else {
-            format!("#{}", quote.channel_name)
-        };
+            guild_channel.mention().to_string()
+        }
+    } else {
+        format!("#{}", quote.channel_name)
+    };
 
     let embed = {
         let mut e = CreateEmbed::default();
@@ -84,3 +164,49 @@ pub(crate) async fn post_quote(
 
     Ok(())
 }
+
+/// Finds quotes in a guild whose id starts with, or whose text contains the input, newest first.
+/// Returns tuples of the id, author and text of every quote, meant for autocompletion.
+pub(crate) async fn find_suggestions(
+    db: &DatabaseConnection,
+    guild_id: GuildId,
+    input: &str,
+    limit: u64,
+) -> Result<Vec<(i64, String, String)>> {
+    let input = input.trim().to_lowercase();
+    let mut query = Quote::find()
+        .select_only()
+        .column(quote::Column::Id)
+        .column(quote::Column::Author)
+        .column(quote::Column::Text)
+        .filter(quote::Column::ServerId.eq(guild_id.get()));
+    if !input.is_empty() {
+        query = query.filter(
+            Condition::any()
+                .add(
+                    Expr::col((quote::Entity, quote::Column::Id))
+                        .cast_as(Alias::new("text"))
+                        .like(like_pattern("", &input, "%")),
+                )
+                .add(Func::lower(Expr::col((quote::Entity, quote::Column::Text))).like(like_pattern("%", &input, "%"))),
+        );
+    }
+
+    Ok(query.order_by_desc(quote::Column::Id).limit(limit).into_tuple().all(db).await?)
+}
+
+/// Builds a LIKE pattern in which the input is taken literally, surrounded by the given wildcards.
+pub(crate) fn like_pattern(prefix: &str, input: &str, suffix: &str) -> LikeExpr {
+    let escaped = input.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    LikeExpr::new(format!("{prefix}{escaped}{suffix}")).escape('\\')
+}
+
+/// Shortens a string to at most `max` characters, marking it with an ellipsis if anything was cut off.
+pub(crate) fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max - 1).collect();
+    truncated.push('…');
+    truncated
+}
@@ -22,3 +22,8 @@ pub async fn set<T: Serialize>(db: &DatabaseConnection, key: &str, val: &T) -> R
         .await?;
     Ok(())
 }
+
+pub async fn delete(db: &DatabaseConnection, key: &str) -> Result<()> {
+    KvStore::delete_by_id(key).exec(db).await?;
+    Ok(())
+}
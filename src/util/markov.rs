@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+
+use rand::{rng, seq::IndexedRandom};
+
+// The amount of words used as state when choosing the next word
+const ORDER: usize = 2;
+// Upper bound on generated sentence length, in case the chain loops
+const MAX_WORDS: usize = 60;
+
+type State = [String; ORDER];
+
+/// A word-level markov chain, trained on a set of sentences.
+#[derive(Default)]
+pub(crate) struct MarkovChain {
+    // The next word for every state, where None marks the end of a sentence
+    transitions: HashMap<State, Vec<Option<String>>>,
+}
+
+impl MarkovChain {
+    pub fn train<'a>(sentences: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut chain = Self::default();
+        for sentence in sentences {
+            let mut state = State::default();
+            let mut words = sentence.split_whitespace().peekable();
+            if words.peek().is_none() {
+                continue;
+            }
+
+            for word in words {
+                chain.transitions.entry(state.clone()).or_default().push(Some(word.to_string()));
+                state.rotate_left(1);
+                state[ORDER - 1] = word.to_string();
+            }
+            chain.transitions.entry(state).or_default().push(None);
+        }
+        chain
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transitions.is_empty()
+    }
+
+    pub fn generate(&self) -> String {
+        let mut rng = rng();
+        let mut state = State::default();
+        let mut words = Vec::new();
+
+        while words.len() < MAX_WORDS {
+            let Some(Some(word)) = self.transitions.get(&state).and_then(|next| next.choose(&mut rng)) else {
+                break;
+            };
+            words.push(word.as_str());
+            state.rotate_left(1);
+            state[ORDER - 1] = word.clone();
+        }
+
+        words.join(" ")
+    }
+}
@@ -1,8 +1,7 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use anyhow::{anyhow, Result};
-use chatgpt::client::ChatGPT;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use sea_orm::DatabaseConnection;
 use serenity::{
     builder::CreateAttachment,
@@ -11,9 +10,11 @@ use serenity::{
     prelude::TypeMapKey,
 };
 use tiktoken_rs::CoreBPE;
-use tokio::{sync::Mutex};
+
+use crate::llm::Llm;
 
 pub mod kvstore;
+pub mod markov;
 
 pub(crate) async fn channel_name(ctx: &Context, id: ChannelId) -> Result<String> {
     if let Channel::Guild(channel) = id.to_channel(&ctx).await? {
@@ -31,6 +32,58 @@ pub(crate) fn convert_bytes_to_attachment(name: impl ToString, bytes: Vec<u8>) -
     CreateAttachment::bytes(bytes, name.to_string())
 }
 
+/// Parses a date in the YYYY-MM-DD format into midnight (UTC) of that day.
+pub(crate) fn parse_date(date: &str) -> Option<DateTime<Utc>> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok().map(|date| date.and_time(NaiveTime::MIN).and_utc())
+}
+
+/// Parses a duration like "90", "10m", "1h30m" or "45s". A plain number is taken as minutes.
+pub(crate) fn parse_duration(duration: &str) -> Option<Duration> {
+    let duration = duration.trim().to_lowercase();
+    if let Ok(minutes) = duration.parse::<u64>() {
+        if minutes == 0 {
+            return None;
+        }
+        return Some(Duration::from_secs(minutes.checked_mul(60)?));
+    }
+
+    let mut total = 0u64;
+    let mut number = String::new();
+    for c in duration.chars().filter(|c| !c.is_whitespace()) {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        total = total.checked_add(number.parse::<u64>().ok()?.checked_mul(unit)?)?;
+        number.clear();
+    }
+
+    // Trailing digits without a unit are ambiguous, so we reject those
+    if !number.is_empty() || total == 0 {
+        return None;
+    }
+    Some(Duration::from_secs(total))
+}
+
+/// Formats a duration as something like "1h 30m", leaving out the parts that are zero.
+pub(crate) fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let parts = [(seconds / 3600, "h"), (seconds / 60 % 60, "m"), (seconds % 60, "s")];
+    let formatted: Vec<String> =
+        parts.iter().filter(|(amount, _)| *amount > 0).map(|(amount, unit)| format!("{amount}{unit}")).collect();
+    if formatted.is_empty() {
+        return "0s".to_string();
+    }
+    formatted.join(" ")
+}
+
 pub(crate) struct DatabaseTypeMapKey;
 
 impl TypeMapKey for DatabaseTypeMapKey {
@@ -40,15 +93,50 @@ impl TypeMapKey for DatabaseTypeMapKey {
 pub(crate) struct TLDRTypeMapKey;
 
 impl TypeMapKey for TLDRTypeMapKey {
-    // ChatGPT is the connection library for GPT
+    // Llm is the configured language model provider
     // CoreBPE is our token counter
-    // The usage status is to throttle usage of the command
-    type Value = (Arc<ChatGPT>, Arc<CoreBPE>, Arc<Mutex<TLDRUsageStatus>>);
+    type Value = (Arc<Llm>, Arc<CoreBPE>);
 }
 
-pub(crate) enum TLDRUsageStatus {
-    Unused,
-    Running(DateTime<Utc>),
-    // These two instants are a timeout for when the command becomes available again
-    Done(DateTime<Utc>),
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{format_duration, parse_duration};
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90"), Some(Duration::from_secs(90 * 60)));
+        assert_eq!(parse_duration("10m"), Some(Duration::from_secs(10 * 60)));
+        assert_eq!(parse_duration("1h30m"), Some(Duration::from_secs(90 * 60)));
+        assert_eq!(parse_duration(" 2H 5s "), Some(Duration::from_secs(2 * 3600 + 5)));
+        assert_eq!(parse_duration("45s"), Some(Duration::from_secs(45)));
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("0"), None);
+        assert_eq!(parse_duration("0m"), None);
+        assert_eq!(parse_duration("1h30"), None);
+        assert_eq!(parse_duration("5d"), None);
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration("99999999999999999999h"), None);
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration(Duration::from_secs(0)), "0s");
+        assert_eq!(format_duration(Duration::from_secs(45)), "45s");
+        assert_eq!(format_duration(Duration::from_secs(90 * 60)), "1h 30m");
+        assert_eq!(format_duration(Duration::from_secs(26 * 3600 + 1)), "26h 1s");
+    }
+
+    #[test]
+    fn formatted_durations_parse_back() {
+        for seconds in [1, 59, 60, 3599, 3600, 5430, 86400] {
+            let duration = Duration::from_secs(seconds);
+            assert_eq!(parse_duration(&format_duration(duration)), Some(duration));
+        }
+    }
 }
@@ -68,6 +68,11 @@ impl Client {
         None
     }
 
+    /// The guild whose members can use the web interface, and whose data it shows.
+    pub fn guild_id(&self) -> GuildId {
+        self.web_whitelist_guild_id
+    }
+
     fn generate_login_redirect(&self) -> HttpResponse {
         let (auth_url, csrf) =
             self.oauth.authorize_url(CsrfToken::new_random).add_scope(Scope::new("identify".to_string())).url();
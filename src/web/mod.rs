@@ -8,6 +8,7 @@ use sea_orm::{prelude::DateTimeWithTimeZone, DatabaseConnection};
 pub mod auth;
 mod image;
 mod index;
+mod readychecks;
 
 pub(crate) fn start(db: DatabaseConnection, auth: auth::Client) -> Result<()> {
     let mut handlebars = Handlebars::new();
@@ -25,6 +26,7 @@ pub(crate) fn start(db: DatabaseConnection, auth: auth::Client) -> Result<()> {
             .app_data(Data::new(auth.clone()))
             .service(index::page)
             .service(image::page)
+            .service(readychecks::page)
             .service(auth::oauth_redirect)
             .service(auth::unauthorized)
             .service(auth::logout)
@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use actix_web::{get, web::Data, HttpRequest, HttpResponse};
+use sea_orm::DatabaseConnection;
+use serenity::json::json;
+
+use crate::{
+    commands::readycheck::stats::{history, member_stats},
+    util::format_duration,
+    web::auth,
+};
+
+// How many of the most recent readychecks we list
+const HISTORY_LIMIT: u64 = 100;
+
+#[get("/readychecks")]
+pub(super) async fn page(
+    req: HttpRequest,
+    auth: Data<auth::Client>,
+    handlebars: Data<handlebars::Handlebars<'_>>,
+    db: Data<DatabaseConnection>,
+) -> HttpResponse {
+    let guild_id = auth.guild_id();
+    if let Some(response) = auth.verify(req).await {
+        return response;
+    }
+
+    let members: Vec<_> = member_stats(db.get_ref(), guild_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|member| {
+            let time_to_ready =
+                member.average_time_to_ready.map(|seconds| format_duration(Duration::from_secs(seconds)));
+            json!({
+                "name": member.name,
+                "readychecks": member.readychecks,
+                "response_rate": member.response_rate,
+                "ready": member.ready,
+                "time_to_ready": time_to_ready.unwrap_or_else(|| "-".to_string()),
+            })
+        })
+        .collect();
+
+    let readychecks: Vec<_> = history(db.get_ref(), guild_id, HISTORY_LIMIT)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|readycheck| {
+            let (ready, total) = readycheck.ready_count().unwrap_or_default();
+            json!({
+                "id": readycheck.id,
+                "ended_at": readycheck.ended_at,
+                "outcome": readycheck.outcome.unwrap_or_else(|| "-".to_string()),
+                "ready": ready,
+                "total": total,
+            })
+        })
+        .collect();
+
+    let rendered =
+        handlebars.render("readychecks", &json!({ "members": members, "readychecks": readychecks })).unwrap();
+    HttpResponse::Ok().body(rendered)
+}